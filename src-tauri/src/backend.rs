@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::compose_cli::{self, ComposeProvider};
+use crate::docker_api::{DockerClient, Endpoint};
+use crate::system;
+
+// Moteur de conteneurs utilisé : Docker ou Podman (rootless ou root).
+// Podman expose une API compatible Docker sur sa propre socket, que Traefik
+// et le client de l'API utilisent comme celle de Docker.
+
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+const PODMAN_ROOT_SOCKET: &str = "/run/podman/podman.sock";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerBackend {
+    #[default]
+    Docker,
+    Podman,
+}
+
+// Sockets Podman possibles : celle de l'utilisateur (rootless) d'abord, puis celle de root
+fn podman_socket_candidates(runtime_dir: Option<&Path>, uid: Option<u32>) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(runtime_dir) = runtime_dir {
+        candidates.push(runtime_dir.join("podman").join("podman.sock"));
+    }
+    if let Some(uid) = uid {
+        let user_socket = PathBuf::from(format!("/run/user/{}/podman/podman.sock", uid));
+        if !candidates.contains(&user_socket) {
+            candidates.push(user_socket);
+        }
+    }
+    candidates.push(PathBuf::from(PODMAN_ROOT_SOCKET));
+    candidates
+}
+
+// Socket Docker à monter dans Traefik : celle de DOCKER_HOST (Docker rootless),
+// sinon celle du démon système. Un démon TCP n'a pas de socket locale.
+fn docker_socket(endpoint: Result<Endpoint, String>) -> PathBuf {
+    match endpoint {
+        Ok(Endpoint::Unix(path)) => path,
+        _ => PathBuf::from(DOCKER_SOCKET),
+    }
+}
+
+#[cfg(unix)]
+fn current_uid() -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata("/proc/self").ok().map(|meta| meta.uid())
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> Option<u32> {
+    None
+}
+
+impl ContainerBackend {
+    pub fn cli(self) -> &'static str {
+        match self {
+            ContainerBackend::Docker => "docker",
+            ContainerBackend::Podman => "podman",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ContainerBackend::Docker => "Docker",
+            ContainerBackend::Podman => "Podman",
+        }
+    }
+
+    // Socket de l'API sur l'hôte. Sans socket Podman active, on renvoie celle de
+    // l'utilisateur pour que l'erreur désigne le bon chemin.
+    pub fn socket_path(self) -> PathBuf {
+        match self {
+            ContainerBackend::Docker => docker_socket(self.endpoint()),
+            ContainerBackend::Podman => {
+                let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+                let candidates = podman_socket_candidates(runtime_dir.as_deref(), current_uid());
+                candidates
+                    .iter()
+                    .find(|socket| socket.exists())
+                    .unwrap_or(&candidates[0])
+                    .clone()
+            }
+        }
+    }
+
+    // DOCKER_HOST reste prioritaire pour Docker, comme pour la CLI
+    pub fn endpoint(self) -> Result<Endpoint, String> {
+        match self {
+            ContainerBackend::Docker => Endpoint::from_env(),
+            ContainerBackend::Podman => Ok(Endpoint::Unix(self.socket_path())),
+        }
+    }
+
+    pub fn client(self) -> Result<DockerClient, String> {
+        Ok(DockerClient::new(self.endpoint()?))
+    }
+
+    // Valeur de DOCKER_HOST à passer aux outils compose, None pour garder l'environnement
+    pub fn docker_host(self) -> Option<String> {
+        match self {
+            ContainerBackend::Docker => None,
+            ContainerBackend::Podman => Some(format!("unix://{}", self.socket_path().display())),
+        }
+    }
+
+    // Ordre de détection des outils compose quand les réglages n'en imposent pas
+    pub fn compose_order(self) -> Vec<ComposeProvider> {
+        match self {
+            ContainerBackend::Docker => compose_cli::DEFAULT_ORDER.to_vec(),
+            ContainerBackend::Podman => vec![
+                ComposeProvider::Podman,
+                ComposeProvider::DockerPlugin,
+                ComposeProvider::DockerStandalone,
+            ],
+        }
+    }
+
+    // Comment démarrer le moteur, pour les messages d'erreur
+    pub fn start_hint(self) -> &'static str {
+        match self {
+            ContainerBackend::Docker => "Please start it first.",
+            ContainerBackend::Podman => {
+                "Please enable its API socket with 'systemctl --user enable --now podman.socket'."
+            }
+        }
+    }
+
+    pub fn is_installed(self) -> bool {
+        system::command_exists(self.cli())
+            || (cfg!(target_os = "windows")
+                && system::command_exists(&format!("{}.exe", self.cli())))
+    }
+
+    pub fn is_running(self) -> bool {
+        self.client()
+            .map(|client| client.ping().is_ok())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn podman_socket_prefers_the_rootless_one() {
+        assert_eq!(
+            podman_socket_candidates(Some(Path::new("/run/user/1000")), Some(1000)),
+            vec![
+                PathBuf::from("/run/user/1000/podman/podman.sock"),
+                PathBuf::from("/run/podman/podman.sock"),
+            ]
+        );
+        assert_eq!(
+            podman_socket_candidates(None, Some(1001)),
+            vec![
+                PathBuf::from("/run/user/1001/podman/podman.sock"),
+                PathBuf::from("/run/podman/podman.sock"),
+            ]
+        );
+    }
+
+    #[test]
+    fn docker_socket_follows_docker_host() {
+        assert_eq!(
+            docker_socket(Endpoint::parse("unix:///run/user/1000/docker.sock")),
+            PathBuf::from("/run/user/1000/docker.sock")
+        );
+        assert_eq!(
+            docker_socket(Endpoint::parse("tcp://192.168.1.10:2375")),
+            PathBuf::from(DOCKER_SOCKET)
+        );
+        assert_eq!(
+            docker_socket(Endpoint::parse("ssh://builder")),
+            PathBuf::from(DOCKER_SOCKET)
+        );
+    }
+
+    #[test]
+    fn podman_prefers_podman_compose() {
+        assert_eq!(
+            ContainerBackend::Podman.compose_order()[0],
+            ComposeProvider::Podman
+        );
+        assert_eq!(
+            serde_json::to_string(&ContainerBackend::Podman).unwrap(),
+            "\"podman\""
+        );
+    }
+}
@@ -0,0 +1,478 @@
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::{Duration, OffsetDateTime};
+use x509_parser::extensions::GeneralName;
+
+use crate::system;
+
+// Autorité de certification locale : une racine créée une seule fois, qui signe
+// un certificat feuille par nom servi par Traefik. Les navigateurs n'ont qu'une
+// racine à approuver, et les noms à plusieurs niveaux (api.client.test) sont couverts.
+
+const CA_DIR: &str = "ca";
+const CA_CERT_FILE: &str = "rootCA.pem";
+const CA_KEY_FILE: &str = "rootCA-key.pem";
+pub const CA_COMMON_NAME: &str = "dev-env local CA";
+const CA_VALIDITY_DAYS: i64 = 3650;
+// Les navigateurs refusent les certificats feuilles de plus de 398 jours
+const LEAF_VALIDITY_DAYS: i64 = 397;
+// Un certificat qui expire dans moins de 30 jours est réémis
+const RENEW_BEFORE_DAYS: i64 = 30;
+const INVENTORY_FILE: &str = "certificates.json";
+
+// Certificat feuille émis par la CA, tel qu'enregistré dans l'inventaire
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CertificateRecord {
+    // Noms couverts, le premier donne le nom des fichiers
+    pub names: Vec<String>,
+    pub cert_file: String,
+    pub key_file: String,
+    // Date d'expiration, en secondes depuis l'epoch
+    pub not_after: i64,
+}
+
+pub struct LocalCa {
+    certificate: Certificate,
+    key: KeyPair,
+    pem: String,
+    not_after: i64,
+    // La racine vient d'être générée : les feuilles existantes ne sont plus valides
+    created: bool,
+}
+
+impl LocalCa {
+    pub fn cert_pem(&self) -> &str {
+        &self.pem
+    }
+
+    pub fn not_after(&self) -> i64 {
+        self.not_after
+    }
+
+    // Certificat et clé PEM pour `names`, avec leur date d'expiration
+    pub fn issue(&self, names: &[String]) -> Result<(String, String, i64), String> {
+        if names.is_empty() {
+            return Err("A certificate needs at least one name".to_string());
+        }
+        let mut params = CertificateParams::new(names.to_vec())
+            .map_err(|e| format!("Failed to prepare certificate for '{}': {}", names[0], e))?;
+        let mut subject = DistinguishedName::new();
+        subject.push(DnType::CommonName, names[0].as_str());
+        params.distinguished_name = subject;
+        params.key_usages = vec![
+            KeyUsagePurpose::DigitalSignature,
+            KeyUsagePurpose::KeyEncipherment,
+        ];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+
+        let now = OffsetDateTime::now_utc();
+        // Le certificat ne peut pas survivre à la racine qui l'a signé
+        let not_after = (now + Duration::days(LEAF_VALIDITY_DAYS))
+            .min(OffsetDateTime::from_unix_timestamp(self.not_after).unwrap_or(now));
+        params.not_before = now - Duration::days(1);
+        params.not_after = not_after;
+
+        let key = KeyPair::generate()
+            .map_err(|e| format!("Failed to generate key for '{}': {}", names[0], e))?;
+        let certificate = params
+            .signed_by(&key, &self.certificate, &self.key)
+            .map_err(|e| format!("Failed to sign certificate for '{}': {}", names[0], e))?;
+
+        Ok((
+            certificate.pem(),
+            key.serialize_pem(),
+            not_after.unix_timestamp(),
+        ))
+    }
+}
+
+pub fn ca_dir() -> Result<PathBuf, String> {
+    Ok(system::get_config_dir()?.join(CA_DIR))
+}
+
+pub fn ca_cert_path() -> Result<PathBuf, String> {
+    Ok(ca_dir()?.join(CA_CERT_FILE))
+}
+
+fn create_ca(dir: &Path) -> Result<LocalCa, String> {
+    let mut params = CertificateParams::default();
+    let mut subject = DistinguishedName::new();
+    subject.push(DnType::CommonName, CA_COMMON_NAME);
+    subject.push(DnType::OrganizationName, "dev-env");
+    params.distinguished_name = subject;
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    let now = OffsetDateTime::now_utc();
+    params.not_before = now - Duration::days(1);
+    params.not_after = now + Duration::days(CA_VALIDITY_DAYS);
+
+    let key = KeyPair::generate().map_err(|e| format!("Failed to generate CA key: {}", e))?;
+    let certificate = params
+        .self_signed(&key)
+        .map_err(|e| format!("Failed to create CA certificate: {}", e))?;
+    let pem = certificate.pem();
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create CA directory: {}", e))?;
+    write_private(&dir.join(CA_KEY_FILE), &key.serialize_pem())?;
+    fs::write(dir.join(CA_CERT_FILE), &pem)
+        .map_err(|e| format!("Failed to write CA certificate: {}", e))?;
+
+    Ok(LocalCa {
+        not_after: certificate.params().not_after.unix_timestamp(),
+        certificate,
+        key,
+        pem,
+        created: true,
+    })
+}
+
+fn load_ca(dir: &Path) -> Result<LocalCa, String> {
+    let pem = fs::read_to_string(dir.join(CA_CERT_FILE))
+        .map_err(|e| format!("Failed to read CA certificate: {}", e))?;
+    let key_pem = fs::read_to_string(dir.join(CA_KEY_FILE))
+        .map_err(|e| format!("Failed to read CA key: {}", e))?;
+
+    let key = KeyPair::from_pem(&key_pem).map_err(|e| format!("Failed to parse CA key: {}", e))?;
+    let params = CertificateParams::from_ca_cert_pem(&pem)
+        .map_err(|e| format!("Failed to parse CA certificate: {}", e))?;
+    let not_after = params.not_after.unix_timestamp();
+    // Re-signer les paramètres relus redonne le même sujet et la même clé,
+    // ce qui suffit pour signer de nouvelles feuilles
+    let certificate = params
+        .self_signed(&key)
+        .map_err(|e| format!("Failed to load CA certificate: {}", e))?;
+
+    Ok(LocalCa {
+        certificate,
+        key,
+        pem,
+        not_after,
+        created: false,
+    })
+}
+
+// Recrée la racine si elle est absente ou expirée
+pub fn load_or_create_ca(dir: &Path) -> Result<LocalCa, String> {
+    if dir.join(CA_CERT_FILE).exists() && dir.join(CA_KEY_FILE).exists() {
+        let ca = load_ca(dir)?;
+        if ca.not_after > OffsetDateTime::now_utc().unix_timestamp() {
+            return Ok(ca);
+        }
+    }
+    create_ca(dir)
+}
+
+// Les clés privées ne sont lisibles que par l'utilisateur
+fn write_private(path: &Path, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to protect '{}': {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+// "*.local.test" -> "_wildcard.local.test", comme mkcert
+fn file_stem(name: &str) -> String {
+    name.replace('*', "_wildcard")
+}
+
+pub fn load_inventory(certs_dir: &Path) -> Vec<CertificateRecord> {
+    fs::read_to_string(certs_dir.join(INVENTORY_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_inventory(certs_dir: &Path, records: &[CertificateRecord]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(records)
+        .map_err(|e| format!("Failed to serialize certificate inventory: {}", e))?;
+    fs::write(certs_dir.join(INVENTORY_FILE), content)
+        .map_err(|e| format!("Failed to write certificate inventory: {}", e))
+}
+
+// Émet les certificats manquants, expirant bientôt ou dont les noms ont changé,
+// conserve les autres et supprime ceux qui ne sont plus demandés
+pub fn issue_certificates(
+    ca: &LocalCa,
+    certs_dir: &Path,
+    requested: &[Vec<String>],
+) -> Result<Vec<CertificateRecord>, String> {
+    fs::create_dir_all(certs_dir)
+        .map_err(|e| format!("Failed to create certificates directory: {}", e))?;
+
+    let previous = if ca.created {
+        Vec::new()
+    } else {
+        load_inventory(certs_dir)
+    };
+    let renew_after = OffsetDateTime::now_utc().unix_timestamp() + RENEW_BEFORE_DAYS * 86_400;
+
+    let mut records = Vec::new();
+    for names in requested.iter().filter(|names| !names.is_empty()) {
+        let stem = file_stem(&names[0]);
+        let reusable = previous.iter().find(|record| {
+            record.names == *names
+                && record.not_after > renew_after
+                && certs_dir.join(&record.cert_file).exists()
+                && certs_dir.join(&record.key_file).exists()
+        });
+        if let Some(record) = reusable {
+            records.push(record.clone());
+            continue;
+        }
+
+        let (cert_pem, key_pem, not_after) = ca.issue(names)?;
+        let record = CertificateRecord {
+            names: names.clone(),
+            cert_file: format!("{}.crt", stem),
+            key_file: format!("{}.key", stem),
+            not_after,
+        };
+        fs::write(certs_dir.join(&record.cert_file), cert_pem)
+            .map_err(|e| format!("Failed to write certificate for '{}': {}", names[0], e))?;
+        write_private(&certs_dir.join(&record.key_file), &key_pem)?;
+        records.push(record);
+    }
+
+    for stale in previous
+        .iter()
+        .filter(|stale| !records.iter().any(|r| r.cert_file == stale.cert_file))
+    {
+        let _ = fs::remove_file(certs_dir.join(&stale.cert_file));
+        let _ = fs::remove_file(certs_dir.join(&stale.key_file));
+    }
+
+    save_inventory(certs_dir, &records)?;
+    Ok(records)
+}
+
+// Certificat lu sur disque, quelle que soit son origine
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CertificateInfo {
+    pub file: String,
+    pub subject: String,
+    pub sans: Vec<String>,
+    pub not_after: i64,
+    pub days_to_expiry: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CertificateInventory {
+    pub certificates: Vec<CertificateInfo>,
+    // Noms servis par Traefik qu'aucun certificat valide ne couvre
+    pub uncovered: Vec<String>,
+    pub needs_renewal: bool,
+}
+
+// Empreintes SHA-256 (AB:CD:...) des certificats PEM trouvés dans un texte,
+// comme les affichent certutil et openssl
+pub fn pem_fingerprints(content: &[u8]) -> Vec<String> {
+    x509_parser::pem::Pem::iter_from_buffer(content)
+        .flatten()
+        .filter(|pem| pem.label == "CERTIFICATE")
+        .map(|pem| {
+            Sha256::digest(&pem.contents)
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<Vec<_>>()
+                .join(":")
+        })
+        .collect()
+}
+
+fn parse_certificate(file: &str, content: &[u8], now: i64) -> Option<CertificateInfo> {
+    let pem = x509_parser::pem::Pem::iter_from_buffer(content)
+        .next()?
+        .ok()?;
+    let certificate = pem.parse_x509().ok()?;
+
+    let sans = match certificate.subject_alternative_name() {
+        Ok(Some(extension)) => extension
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(dns) => Some(dns.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let not_after = certificate.validity().not_after.timestamp();
+
+    Some(CertificateInfo {
+        file: file.to_string(),
+        subject: certificate.subject().to_string(),
+        sans,
+        not_after,
+        days_to_expiry: (not_after - now).div_euclid(86_400),
+    })
+}
+
+// Un joker ne couvre qu'un seul niveau : *.local.test couvre shop.local.test,
+// pas api.shop.local.test
+fn covers(name: &str, hostname: &str) -> bool {
+    match name.strip_prefix("*.") {
+        Some(domain) => hostname
+            .split_once('.')
+            .map(|(label, rest)| !label.is_empty() && rest == domain)
+            .unwrap_or(false),
+        None => name == hostname,
+    }
+}
+
+pub fn inspect_certificates(
+    certs_dir: &Path,
+    hostnames: &[String],
+) -> Result<CertificateInventory, String> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut certificates = Vec::new();
+
+    if certs_dir.exists() {
+        let entries = fs::read_dir(certs_dir)
+            .map_err(|e| format!("Failed to read certificates directory: {}", e))?;
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("crt") | Some("pem")
+                )
+            })
+            .collect();
+        files.sort();
+
+        for path in files {
+            let file = path.file_name().unwrap_or_default().to_string_lossy();
+            let content = fs::read(&path)
+                .map_err(|e| format!("Failed to read certificate '{}': {}", file, e))?;
+            certificates.extend(parse_certificate(&file, &content, now));
+        }
+    }
+
+    let uncovered: Vec<String> = hostnames
+        .iter()
+        .filter(|hostname| {
+            !certificates.iter().any(|certificate| {
+                certificate.days_to_expiry >= 0
+                    && certificate.sans.iter().any(|name| covers(name, hostname))
+            })
+        })
+        .cloned()
+        .collect();
+    // Seuls les certificats émis par la CA locale peuvent être renouvelés
+    let managed = load_inventory(certs_dir);
+    let needs_renewal = !uncovered.is_empty()
+        || certificates.iter().any(|certificate| {
+            certificate.days_to_expiry < RENEW_BEFORE_DAYS
+                && managed
+                    .iter()
+                    .any(|record| record.cert_file == certificate.file)
+        });
+
+    Ok(CertificateInventory {
+        certificates,
+        uncovered,
+        needs_renewal,
+    })
+}
+
+// Configuration dynamique Traefik : un certificat par entrée, choisi par SNI
+pub fn traefik_tls_config(records: &[CertificateRecord], mount_dir: &str) -> String {
+    let mut config = String::new();
+    for record in records {
+        config.push_str(&format!(
+            "[[tls.certificates]]\n  certFile = \"{dir}/{}\"\n  keyFile = \"{dir}/{}\"\n\n",
+            record.cert_file,
+            record.key_file,
+            dir = mount_dir
+        ));
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dev-env-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn issues_reuses_and_prunes_leaf_certificates() {
+        let dir = temp_dir("certs");
+        let ca = load_or_create_ca(&dir.join("ca")).unwrap();
+        let certs_dir = dir.join("certs");
+
+        let wildcard = vec!["*.local.test".to_string(), "local.test".to_string()];
+        let alias = vec!["api.client.test".to_string()];
+        let records = issue_certificates(&ca, &certs_dir, &[wildcard.clone(), alias]).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert!(ca.issue(&[]).is_err());
+        assert_eq!(records[0].cert_file, "_wildcard.local.test.crt");
+        assert_eq!(records[1].key_file, "api.client.test.key");
+        assert!(records[1].not_after <= ca.not_after());
+        let leaf = fs::read_to_string(certs_dir.join("api.client.test.crt")).unwrap();
+        assert!(leaf.starts_with("-----BEGIN CERTIFICATE-----"));
+        let bundle = format!("label: test\n{}\n{}", ca.cert_pem(), leaf);
+        let fingerprints = pem_fingerprints(bundle.as_bytes());
+        assert_eq!(fingerprints.len(), 2);
+        assert_eq!(fingerprints[0].len(), 32 * 3 - 1);
+        assert_ne!(fingerprints[0], fingerprints[1]);
+
+        // La racine est relue depuis le disque et les feuilles valides sont conservées
+        let reloaded = load_or_create_ca(&dir.join("ca")).unwrap();
+        assert_eq!(reloaded.cert_pem(), ca.cert_pem());
+        let kept = issue_certificates(&reloaded, &certs_dir, &[wildcard]).unwrap();
+        assert_eq!(kept, vec![records[0].clone()]);
+        assert_eq!(load_inventory(&certs_dir), kept);
+        assert!(!certs_dir.join("api.client.test.crt").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn inventory_reports_sans_expiry_and_uncovered_hostnames() {
+        let dir = temp_dir("inventory");
+        let ca = load_or_create_ca(&dir.join("ca")).unwrap();
+        let certs_dir = dir.join("certs");
+        let wildcard = vec!["*.local.test".to_string(), "local.test".to_string()];
+        issue_certificates(&ca, &certs_dir, &[wildcard]).unwrap();
+
+        let hostnames = vec![
+            "shop.local.test".to_string(),
+            "api.shop.local.test".to_string(),
+        ];
+        let inventory = inspect_certificates(&certs_dir, &hostnames).unwrap();
+
+        assert_eq!(inventory.certificates.len(), 1);
+        let certificate = &inventory.certificates[0];
+        assert_eq!(certificate.file, "_wildcard.local.test.crt");
+        assert_eq!(certificate.subject, "CN=*.local.test");
+        assert_eq!(certificate.sans, vec!["*.local.test", "local.test"]);
+        assert!(certificate.days_to_expiry > RENEW_BEFORE_DAYS);
+        assert_eq!(inventory.uncovered, vec!["api.shop.local.test"]);
+        assert!(inventory.needs_renewal);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,995 @@
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping as YamlMap, Value as YamlValue};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Modèle typé d'un fichier docker-compose.yml.
+// Toutes les maps sont des BTreeMap et les champs sont déclarés dans l'ordre
+// alphabétique pour que la sérialisation soit déterministe.
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ComposeFile {
+    #[serde(
+        default,
+        deserialize_with = "null_values_as_default",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub networks: BTreeMap<String, Network>,
+    #[serde(default)]
+    pub services: BTreeMap<String, ComposeService>,
+    #[serde(
+        default,
+        deserialize_with = "null_values_as_default",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub volumes: BTreeMap<String, Volume>,
+    // Clés non modélisées (version, name, secrets...) conservées telles quelles
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, YamlValue>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ComposeService {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    // Équivalents v2 de deploy.resources.limits, seuls lus par docker-compose v1
+    #[serde(
+        default,
+        deserialize_with = "optional_scalar",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpus: Option<String>,
+    #[serde(default, skip_serializing_if = "DependsOn::is_empty")]
+    pub depends_on: DependsOn,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deploy: Option<Deploy>,
+    #[serde(
+        default,
+        deserialize_with = "key_value_map",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub environment: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<Healthcheck>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "key_value_map",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub labels: BTreeMap<String, String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_scalar",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mem_limit: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "network_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub networks: Vec<String>,
+    #[serde(
+        default,
+        deserialize_with = "port_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub ports: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "volume_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub volumes: Vec<String>,
+    // Clés non modélisées (build, command, entrypoint...) conservées telles quelles
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, YamlValue>,
+}
+
+// `depends_on` accepte une liste de services ou une map avec conditions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum DependsOn {
+    List(Vec<String>),
+    Conditions(BTreeMap<String, DependsOnCondition>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DependsOnCondition {
+    pub condition: String,
+}
+
+impl Default for DependsOn {
+    fn default() -> Self {
+        DependsOn::List(Vec::new())
+    }
+}
+
+impl DependsOn {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            DependsOn::List(services) => services.is_empty(),
+            DependsOn::Conditions(services) => services.is_empty(),
+        }
+    }
+
+    pub fn services(&self) -> Vec<String> {
+        match self {
+            DependsOn::List(services) => services.clone(),
+            DependsOn::Conditions(services) => services.keys().cloned().collect(),
+        }
+    }
+
+    fn merge(&mut self, other: DependsOn) {
+        match (&mut *self, other) {
+            (DependsOn::Conditions(current), DependsOn::Conditions(other)) => current.extend(other),
+            (DependsOn::Conditions(current), DependsOn::List(other)) => {
+                for service in other {
+                    current.entry(service).or_insert(DependsOnCondition {
+                        condition: "service_started".to_string(),
+                    });
+                }
+            }
+            (DependsOn::List(current), DependsOn::Conditions(mut other)) => {
+                for service in current.drain(..) {
+                    other.entry(service).or_insert(DependsOnCondition {
+                        condition: "service_started".to_string(),
+                    });
+                }
+                *self = DependsOn::Conditions(other);
+            }
+            (DependsOn::List(current), DependsOn::List(other)) => append_unique(current, other),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Healthcheck {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_period: Option<String>,
+    #[serde(default, deserialize_with = "healthcheck_test")]
+    pub test: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Deploy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Resources>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Resources {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ResourceLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reservations: Option<ResourceLimits>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    #[serde(
+        default,
+        deserialize_with = "optional_scalar",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpus: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Network {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // Clés non modélisées (driver_opts, ipam, attachable, labels...) conservées telles quelles
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, YamlValue>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Volume {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // Clés non modélisées (driver_opts, labels...) conservées telles quelles
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, YamlValue>,
+}
+
+// Résultat de la fusion du compose généré avec les overrides
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ComposeValidation {
+    pub merged: String,
+    pub errors: Vec<String>,
+}
+
+// Modification d'un champ entre deux versions du compose ; `before` ou `after`
+// est absent quand le champ est ajouté ou supprimé
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<YamlValue>,
+    pub after: Option<YamlValue>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServiceChanges {
+    pub service: String,
+    pub changes: Vec<FieldChange>,
+}
+
+// Différences entre le compose sur disque et celui qui va être généré
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ComposeDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ServiceChanges>,
+    // Réseaux, volumes et clés non modélisées
+    pub top_level: Vec<FieldChange>,
+    // Le fichier sur disque n'est plus celui enregistré par dev-env
+    #[serde(default)]
+    pub edited_by_hand: bool,
+}
+
+impl ComposeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.top_level.is_empty()
+    }
+}
+
+fn key_to_string(key: &YamlValue) -> String {
+    scalar_to_string(key).unwrap_or_else(|| format!("{:?}", key))
+}
+
+// Compare deux valeurs champ par champ : les maps sont parcourues récursivement
+// ("environment.DB_HOST"), les listes et scalaires sont comparés en entier
+fn value_changes(
+    field: &str,
+    before: Option<&YamlValue>,
+    after: Option<&YamlValue>,
+) -> Vec<FieldChange> {
+    match (before, after) {
+        (Some(YamlValue::Mapping(before)), Some(YamlValue::Mapping(after))) => {
+            mapping_changes(field, before, after)
+        }
+        (before, after) if before == after => Vec::new(),
+        (before, after) => vec![FieldChange {
+            field: field.to_string(),
+            before: before.cloned(),
+            after: after.cloned(),
+        }],
+    }
+}
+
+fn mapping_changes(prefix: &str, before: &YamlMap, after: &YamlMap) -> Vec<FieldChange> {
+    let mut keys: Vec<&YamlValue> = before.keys().chain(after.keys()).collect();
+    keys.sort_by_key(|key| key_to_string(key));
+    keys.dedup();
+
+    keys.into_iter()
+        .flat_map(|key| {
+            let name = key_to_string(key);
+            let field = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            value_changes(&field, before.get(key), after.get(key))
+        })
+        .collect()
+}
+
+// Le modèle est sérialisable sans erreur : toutes ses clés sont des chaînes
+fn to_mapping<T: Serialize>(value: &T) -> YamlMap {
+    match serde_yaml::to_value(value) {
+        Ok(YamlValue::Mapping(mapping)) => mapping,
+        _ => YamlMap::new(),
+    }
+}
+
+// Ajoute les entrées absentes d'une liste en conservant l'ordre
+fn append_unique(target: &mut Vec<String>, entries: Vec<String>) {
+    for entry in entries {
+        if !target.contains(&entry) {
+            target.push(entry);
+        }
+    }
+}
+
+// Chemin dans le conteneur d'une entrée `source:cible[:mode]`
+fn volume_target(volume: &str) -> &str {
+    volume.split(':').nth(1).unwrap_or(volume)
+}
+
+impl ComposeService {
+    // Fusion façon docker-compose : les scalaires sont remplacés, les maps
+    // fusionnées clé par clé et les listes complétées
+    pub fn merge(&mut self, other: ComposeService) {
+        if other.container_name.is_some() {
+            self.container_name = other.container_name;
+        }
+        if other.cpus.is_some() {
+            self.cpus = other.cpus;
+        }
+        self.depends_on.merge(other.depends_on);
+        if other.deploy.is_some() {
+            self.deploy = other.deploy;
+        }
+        self.environment.extend(other.environment);
+        if other.healthcheck.is_some() {
+            self.healthcheck = other.healthcheck;
+        }
+        if other.image.is_some() {
+            self.image = other.image;
+        }
+        self.labels.extend(other.labels);
+        if other.mem_limit.is_some() {
+            self.mem_limit = other.mem_limit;
+        }
+        append_unique(&mut self.networks, other.networks);
+        append_unique(&mut self.ports, other.ports);
+        if other.restart.is_some() {
+            self.restart = other.restart;
+        }
+        // Un volume monté sur la même cible remplace le volume existant
+        for volume in other.volumes {
+            let target = volume_target(&volume).to_string();
+            self.volumes.retain(|v| volume_target(v) != target);
+            self.volumes.push(volume);
+        }
+        self.extra.extend(other.extra);
+    }
+}
+
+// Port publié sur l'hôte par un service
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PublishedPort {
+    pub service: String,
+    pub port: u16,
+    pub protocol: String,
+}
+
+// Ports hôte d'une entrée `[ip:]hôte:conteneur[/protocole]`, plages comprises.
+// Une entrée sans port hôte publie un port éphémère et n'est pas retenue.
+fn host_ports(entry: &str) -> (Vec<u16>, String) {
+    let (mapping, protocol) = match entry.split_once('/') {
+        Some((mapping, protocol)) => (mapping, protocol.to_string()),
+        None => (entry, "tcp".to_string()),
+    };
+    // Une IP hôte IPv6 est entre crochets et contient elle-même des ':'
+    let mapping = match mapping.strip_prefix('[') {
+        Some(bracketed) => match bracketed.split_once("]:") {
+            Some((_, rest)) => rest,
+            None => return (Vec::new(), protocol),
+        },
+        None => mapping,
+    };
+    let parts: Vec<&str> = mapping.rsplitn(3, ':').collect();
+    let host = match parts.get(1) {
+        Some(host) if !host.is_empty() => *host,
+        _ => return (Vec::new(), protocol),
+    };
+
+    let ports = match host.split_once('-') {
+        Some((start, end)) => match (start.parse::<u16>(), end.parse::<u16>()) {
+            (Ok(start), Ok(end)) if start <= end => (start..=end).collect(),
+            _ => Vec::new(),
+        },
+        None => host.parse::<u16>().into_iter().collect(),
+    };
+    (ports, protocol)
+}
+
+impl ComposeFile {
+    pub fn published_ports(&self) -> Vec<PublishedPort> {
+        let mut published = Vec::new();
+        for (name, service) in &self.services {
+            for entry in &service.ports {
+                let (ports, protocol) = host_ports(entry);
+                for port in ports {
+                    published.push(PublishedPort {
+                        service: name.clone(),
+                        port,
+                        protocol: protocol.clone(),
+                    });
+                }
+            }
+        }
+        published
+    }
+
+    pub fn merge(&mut self, other: ComposeFile) {
+        self.networks.extend(other.networks);
+        for (name, service) in other.services {
+            match self.services.get_mut(&name) {
+                Some(current) => current.merge(service),
+                None => {
+                    self.services.insert(name, service);
+                }
+            }
+        }
+        self.volumes.extend(other.volumes);
+        self.extra.extend(other.extra);
+    }
+
+    // Erreurs qui empêcheraient docker-compose de démarrer le fichier
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (name, service) in &self.services {
+            if service.image.is_none() && !service.extra.contains_key("build") {
+                errors.push(format!(
+                    "Service '{}' has neither an image nor a build",
+                    name
+                ));
+            }
+            for dependency in service.depends_on.services() {
+                if !self.services.contains_key(&dependency) {
+                    errors.push(format!(
+                        "Service '{}' depends on undefined service '{}'",
+                        name, dependency
+                    ));
+                }
+            }
+            for network in &service.networks {
+                if network != "default" && !self.networks.contains_key(network) {
+                    errors.push(format!(
+                        "Service '{}' uses undefined network '{}'",
+                        name, network
+                    ));
+                }
+            }
+            for volume in &service.volumes {
+                let source = volume.split(':').next().unwrap_or_default();
+                let is_named = volume.contains(':')
+                    && !source.starts_with('.')
+                    && !source.starts_with('/')
+                    && !source.starts_with('~');
+                if is_named && !self.volumes.contains_key(source) {
+                    errors.push(format!(
+                        "Service '{}' uses undefined volume '{}'",
+                        name, source
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    // Services nécessaires au démarrage de `roots`, chaque service après ses dépendances
+    pub fn with_dependencies(&self, roots: &[String]) -> Vec<String> {
+        fn visit(
+            compose: &ComposeFile,
+            name: &str,
+            seen: &mut BTreeSet<String>,
+            order: &mut Vec<String>,
+        ) {
+            // `seen` est rempli avant les dépendances pour ne pas boucler sur un cycle
+            if !compose.services.contains_key(name) || !seen.insert(name.to_string()) {
+                return;
+            }
+            for dependency in compose.services[name].depends_on.services() {
+                visit(compose, &dependency, seen, order);
+            }
+            order.push(name.to_string());
+        }
+
+        let mut seen = BTreeSet::new();
+        let mut order = Vec::new();
+        for root in roots {
+            visit(self, root, &mut seen, &mut order);
+        }
+        order
+    }
+
+    // Vrai si le fichier sur disque diffère de la dernière version enregistrée par
+    // dev-env ; la mise en forme et l'ordre des clés ne comptent pas
+    pub fn edited_by_hand(on_disk: &str, last_saved: Option<&str>) -> Result<bool, String> {
+        match last_saved {
+            Some(last_saved) => {
+                Ok(ComposeFile::from_yaml(on_disk)? != ComposeFile::from_yaml(last_saved)?)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Changements pour passer de ce fichier à `other`
+    pub fn diff(&self, other: &ComposeFile) -> ComposeDiff {
+        let mut diff = ComposeDiff::default();
+
+        for (name, after) in &other.services {
+            match self.services.get(name) {
+                None => diff.added.push(name.clone()),
+                Some(before) => {
+                    let changes = mapping_changes("", &to_mapping(before), &to_mapping(after));
+                    if !changes.is_empty() {
+                        diff.changed.push(ServiceChanges {
+                            service: name.clone(),
+                            changes,
+                        });
+                    }
+                }
+            }
+        }
+        diff.removed = self
+            .services
+            .keys()
+            .filter(|name| !other.services.contains_key(*name))
+            .cloned()
+            .collect();
+
+        let mut before = to_mapping(self);
+        let mut after = to_mapping(other);
+        before.remove("services");
+        after.remove("services");
+        diff.top_level = mapping_changes("", &before, &after);
+
+        diff
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self, String> {
+        serde_yaml::from_str(content)
+            .map_err(|e| format!("Failed to parse Docker Compose file: {}", e))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self)
+            .map_err(|e| format!("Failed to generate Docker Compose file: {}", e))
+    }
+}
+
+// Convertit un scalaire YAML (chaîne, nombre, booléen) en chaîne
+fn scalar_to_string(value: &YamlValue) -> Option<String> {
+    match value {
+        YamlValue::String(s) => Some(s.clone()),
+        YamlValue::Number(n) => Some(n.to_string()),
+        YamlValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn null_values_as_default<'de, D, T>(deserializer: D) -> Result<BTreeMap<String, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    let values = BTreeMap::<String, Option<T>>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .map(|(key, value)| (key, value.unwrap_or_default()))
+        .collect())
+}
+
+fn optional_scalar<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<YamlValue>::deserialize(deserializer)?;
+    Ok(value.as_ref().and_then(scalar_to_string))
+}
+
+// `environment` et `labels` : map `KEY: value` ou liste `KEY=value`
+fn key_value_map<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut variables = BTreeMap::new();
+    match YamlValue::deserialize(deserializer)? {
+        YamlValue::Null => {}
+        YamlValue::Mapping(map) => {
+            for (key, value) in map {
+                let key = scalar_to_string(&key)
+                    .ok_or_else(|| de::Error::custom("expected a scalar key"))?;
+                variables.insert(key, scalar_to_string(&value).unwrap_or_default());
+            }
+        }
+        YamlValue::Sequence(entries) => {
+            for entry in entries {
+                let entry = scalar_to_string(&entry)
+                    .ok_or_else(|| de::Error::custom("expected a KEY=value entry"))?;
+                match entry.split_once('=') {
+                    Some((key, value)) => variables.insert(key.to_string(), value.to_string()),
+                    None => variables.insert(entry, String::new()),
+                };
+            }
+        }
+        _ => return Err(de::Error::custom("expected a map or a list")),
+    }
+    Ok(variables)
+}
+
+fn network_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match YamlValue::deserialize(deserializer)? {
+        YamlValue::Null => Ok(Vec::new()),
+        YamlValue::Sequence(entries) => Ok(entries.iter().filter_map(scalar_to_string).collect()),
+        YamlValue::Mapping(map) => Ok(map.keys().filter_map(scalar_to_string).collect()),
+        _ => Err(de::Error::custom("expected a list or a map of networks")),
+    }
+}
+
+// Liste de chaînes dont les entrées en syntaxe longue sont converties en syntaxe courte
+fn short_syntax_list<'de, D>(
+    deserializer: D,
+    long_syntax: fn(&YamlMap) -> Option<String>,
+) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let entries = Option::<Vec<YamlValue>>::deserialize(deserializer)?.unwrap_or_default();
+    entries
+        .iter()
+        .map(|entry| match entry {
+            YamlValue::Mapping(map) => long_syntax(map),
+            other => scalar_to_string(other),
+        })
+        .map(|entry| entry.ok_or_else(|| de::Error::custom("unsupported list entry")))
+        .collect()
+}
+
+fn field(map: &YamlMap, key: &str) -> Option<String> {
+    map.get(key).and_then(scalar_to_string)
+}
+
+fn port_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    short_syntax_list(deserializer, |map| {
+        let target = field(map, "target")?;
+        let mut port = match field(map, "published") {
+            Some(published) => format!("{}:{}", published, target),
+            None => target,
+        };
+        if let Some(protocol) = field(map, "protocol") {
+            port = format!("{}/{}", port, protocol);
+        }
+        Some(port)
+    })
+}
+
+fn volume_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    short_syntax_list(deserializer, |map| {
+        let target = field(map, "target")?;
+        let mut volume = match field(map, "source") {
+            Some(source) => format!("{}:{}", source, target),
+            None => target,
+        };
+        if map.get("read_only") == Some(&YamlValue::Bool(true)) {
+            volume.push_str(":ro");
+        }
+        Some(volume)
+    })
+}
+
+// `test` accepte une liste ou une commande shell
+fn healthcheck_test<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match YamlValue::deserialize(deserializer)? {
+        YamlValue::Null => Ok(Vec::new()),
+        YamlValue::String(command) => Ok(vec!["CMD-SHELL".to_string(), command]),
+        YamlValue::Sequence(entries) => Ok(entries.iter().filter_map(scalar_to_string).collect()),
+        _ => Err(de::Error::custom("expected a string or a list")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_short_and_long_syntax() {
+        let compose = ComposeFile::from_yaml(
+            r#"
+version: "3.8"
+services:
+  db:
+    image: mysql:8
+    environment:
+      - MYSQL_DATABASE=app
+      - MYSQL_ALLOW_EMPTY_PASSWORD
+    ports:
+      - 3306
+      - target: 80
+        published: 8081
+        protocol: tcp
+    volumes:
+      - type: volume
+        source: db_data
+        target: /var/lib/mysql
+        read_only: true
+    healthcheck:
+      test: mysqladmin ping
+      retries: 5
+    networks:
+      default:
+    build: .
+  app:
+    image: php:8.3-fpm
+    depends_on:
+      db:
+        condition: service_healthy
+volumes:
+  db_data:
+  cache:
+    driver_opts:
+      type: tmpfs
+      device: tmpfs
+networks:
+  frontend:
+  backend:
+    driver: bridge
+    attachable: true
+    ipam:
+      config:
+        - subnet: 172.28.0.0/16
+"#,
+        )
+        .unwrap();
+
+        let db = &compose.services["db"];
+        assert_eq!(db.environment["MYSQL_DATABASE"], "app");
+        assert_eq!(db.environment["MYSQL_ALLOW_EMPTY_PASSWORD"], "");
+        assert_eq!(db.ports, vec!["3306", "8081:80/tcp"]);
+        assert_eq!(db.volumes, vec!["db_data:/var/lib/mysql:ro"]);
+        assert_eq!(
+            db.healthcheck.as_ref().unwrap().test,
+            vec!["CMD-SHELL", "mysqladmin ping"]
+        );
+        assert_eq!(db.networks, vec!["default"]);
+        assert!(db.extra.contains_key("build"));
+        assert_eq!(compose.services["app"].depends_on.services(), vec!["db"]);
+        assert_eq!(compose.volumes["db_data"], Volume::default());
+        assert!(compose.volumes["cache"].extra.contains_key("driver_opts"));
+        assert_eq!(compose.networks["frontend"], Network::default());
+        let backend = &compose.networks["backend"];
+        assert_eq!(backend.driver.as_deref(), Some("bridge"));
+        assert!(backend.extra.contains_key("attachable") && backend.extra.contains_key("ipam"));
+        let yaml = compose.to_yaml().unwrap();
+        assert!(yaml.contains("subnet: 172.28.0.0/16") && yaml.contains("type: tmpfs"));
+        assert!(compose.extra.contains_key("version"));
+    }
+
+    #[test]
+    fn override_is_merged_and_validated() {
+        let mut compose = ComposeFile::from_yaml(
+            r#"
+services:
+  php_shop:
+    image: php:8.2-fpm
+    environment:
+      APP_ENV: dev
+    volumes: ["./projects/shop:/var/www/html"]
+    networks: [dev_env_network]
+networks:
+  dev_env_network:
+    driver: bridge
+"#,
+        )
+        .unwrap();
+        let overrides = ComposeFile::from_yaml(
+            r#"
+services:
+  php_shop:
+    image: php:8.3-fpm
+    environment:
+      XDEBUG_MODE: debug
+    volumes: ["/home/me/shop:/var/www/html", "cache:/tmp/cache"]
+    depends_on: [queue]
+"#,
+        )
+        .unwrap();
+
+        compose.merge(overrides);
+        let php = &compose.services["php_shop"];
+        assert_eq!(php.image.as_deref(), Some("php:8.3-fpm"));
+        assert_eq!(php.environment.len(), 2);
+        assert_eq!(
+            php.volumes,
+            vec!["/home/me/shop:/var/www/html", "cache:/tmp/cache"]
+        );
+        assert_eq!(
+            compose.validate(),
+            vec![
+                "Service 'php_shop' depends on undefined service 'queue'",
+                "Service 'php_shop' uses undefined volume 'cache'",
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_host_published_ports() {
+        let compose = ComposeFile::from_yaml(
+            r#"
+services:
+  traefik:
+    ports: ["80:80", "127.0.0.1:8080:8080", "[::1]:8443:443", "[::1]::5432"]
+  dns:
+    ports: ["5353:53/udp", "9000-9001:9000-9001", "3306"]
+"#,
+        )
+        .unwrap();
+
+        let ports: Vec<(String, u16, String)> = compose
+            .published_ports()
+            .into_iter()
+            .map(|p| (p.service, p.port, p.protocol))
+            .collect();
+        assert_eq!(
+            ports,
+            vec![
+                ("dns".to_string(), 5353, "udp".to_string()),
+                ("dns".to_string(), 9000, "tcp".to_string()),
+                ("dns".to_string(), 9001, "tcp".to_string()),
+                ("traefik".to_string(), 80, "tcp".to_string()),
+                ("traefik".to_string(), 8080, "tcp".to_string()),
+                ("traefik".to_string(), 8443, "tcp".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_services_after_their_dependencies() {
+        let compose = ComposeFile::from_yaml(
+            r#"
+services:
+  app:
+    image: php
+    depends_on: [db, cache]
+  cache:
+    image: redis
+  db:
+    image: mysql
+    depends_on:
+      storage:
+        condition: service_healthy
+  storage:
+    image: minio
+  unrelated:
+    image: alpine
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            compose.with_dependencies(&["app".to_string(), "missing".to_string()]),
+            vec!["storage", "db", "cache", "app"]
+        );
+    }
+
+    #[test]
+    fn detects_hand_edits_since_last_save() {
+        let saved = "services:\n  db:\n    image: mysql:8\n    ports: [\"3306:3306\"]\n";
+        let reformatted = "services:\n  db:\n    ports:\n      - 3306:3306\n    image: mysql:8\n";
+        let edited = "services:\n  db:\n    image: mysql:8.4\n    ports: [\"3306:3306\"]\n";
+
+        assert_eq!(
+            ComposeFile::edited_by_hand(reformatted, Some(saved)),
+            Ok(false)
+        );
+        assert_eq!(ComposeFile::edited_by_hand(edited, Some(saved)), Ok(true));
+        assert_eq!(ComposeFile::edited_by_hand(edited, None), Ok(false));
+    }
+
+    #[test]
+    fn diffs_services_field_by_field() {
+        let on_disk = ComposeFile::from_yaml(
+            r#"
+services:
+  db:
+    image: mysql:8
+    environment:
+      MYSQL_DATABASE: shop
+      MYSQL_USER: dev
+    ports: ["3306:3306"]
+  legacy:
+    image: memcached
+volumes:
+  db_data: {}
+"#,
+        )
+        .unwrap();
+        let generated = ComposeFile::from_yaml(
+            r#"
+services:
+  db:
+    image: mysql:8
+    environment:
+      - MYSQL_DATABASE=shop
+      - MYSQL_PASSWORD=secret
+    ports: ["3307:3306"]
+  redis:
+    image: redis
+"#,
+        )
+        .unwrap();
+
+        let diff = on_disk.diff(&generated);
+        assert_eq!(diff.added, vec!["redis"]);
+        assert_eq!(diff.removed, vec!["legacy"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].service, "db");
+
+        let fields: Vec<(&str, Option<&YamlValue>, Option<&YamlValue>)> = diff.changed[0]
+            .changes
+            .iter()
+            .map(|change| {
+                (
+                    change.field.as_str(),
+                    change.before.as_ref(),
+                    change.after.as_ref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (
+                    "environment.MYSQL_PASSWORD",
+                    None,
+                    Some(&YamlValue::from("secret"))
+                ),
+                (
+                    "environment.MYSQL_USER",
+                    Some(&YamlValue::from("dev")),
+                    None
+                ),
+                (
+                    "ports",
+                    Some(&YamlValue::Sequence(vec!["3306:3306".into()])),
+                    Some(&YamlValue::Sequence(vec!["3307:3306".into()]))
+                ),
+            ]
+        );
+        assert_eq!(diff.top_level.len(), 1);
+        assert_eq!(diff.top_level[0].field, "volumes");
+
+        assert!(generated.diff(&generated).is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_yaml() {
+        let mut compose = ComposeFile::default();
+        let mut service = ComposeService {
+            image: Some("redis:latest".to_string()),
+            networks: vec!["dev_env_network".to_string()],
+            ..Default::default()
+        };
+        service
+            .labels
+            .insert("traefik.enable".to_string(), "false".to_string());
+        compose.services.insert("redis".to_string(), service);
+        compose
+            .volumes
+            .insert("redis_data".to_string(), Volume::default());
+
+        let yaml = compose.to_yaml().unwrap();
+        assert_eq!(ComposeFile::from_yaml(&yaml).unwrap(), compose);
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::process::{Command, Output};
+
+use crate::system;
+
+// Outil compose utilisé pour piloter l'environnement : le plugin `docker compose`,
+// l'ancien binaire `docker-compose` ou `podman-compose`, détecté dans l'ordre configuré
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComposeProvider {
+    #[serde(rename = "docker compose")]
+    DockerPlugin,
+    #[serde(rename = "docker-compose")]
+    DockerStandalone,
+    #[serde(rename = "podman-compose")]
+    Podman,
+}
+
+pub const DEFAULT_ORDER: [ComposeProvider; 3] = [
+    ComposeProvider::DockerPlugin,
+    ComposeProvider::DockerStandalone,
+    ComposeProvider::Podman,
+];
+
+impl ComposeProvider {
+    fn program(self) -> &'static str {
+        match self {
+            ComposeProvider::DockerPlugin => "docker",
+            ComposeProvider::DockerStandalone => "docker-compose",
+            ComposeProvider::Podman => "podman-compose",
+        }
+    }
+
+    // Arguments placés avant ceux de compose (`docker compose ...`)
+    fn prefix_args(self) -> &'static [&'static str] {
+        match self {
+            ComposeProvider::DockerPlugin => &["compose"],
+            _ => &[],
+        }
+    }
+
+    // Sans le plugin, `docker compose --version` peut réussir sur l'aide de docker :
+    // la sous-commande `version` échoue bien si le plugin manque
+    fn version_args(self) -> &'static [&'static str] {
+        match self {
+            ComposeProvider::DockerPlugin => &["compose", "version"],
+            _ => &["--version"],
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ComposeProvider::DockerPlugin => "docker compose",
+            ComposeProvider::DockerStandalone => "docker-compose",
+            ComposeProvider::Podman => "podman-compose",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ComposeInvoker {
+    pub provider: ComposeProvider,
+    pub version: String,
+    // Socket du moteur pour les outils qui parlent l'API Docker (Podman)
+    #[serde(skip)]
+    pub docker_host: Option<String>,
+}
+
+// Version rapportée par l'outil, ou None s'il n'est pas utilisable
+fn probe(provider: ComposeProvider) -> Option<String> {
+    let output = Command::new(provider.program())
+        .args(provider.version_args())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // podman-compose affiche aussi la version de podman sur une seconde ligne
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+fn detect_with(
+    order: &[ComposeProvider],
+    probe: impl Fn(ComposeProvider) -> Option<String>,
+) -> Result<ComposeInvoker, String> {
+    let order = if order.is_empty() {
+        &DEFAULT_ORDER[..]
+    } else {
+        order
+    };
+    order
+        .iter()
+        .find_map(|&provider| {
+            probe(provider).map(|version| ComposeInvoker {
+                provider,
+                version,
+                docker_host: None,
+            })
+        })
+        .ok_or_else(|| {
+            let tried: Vec<&str> = order.iter().map(|provider| provider.label()).collect();
+            format!(
+                "No compose tool found. Install Docker Compose or podman-compose (tried: {})",
+                tried.join(", ")
+            )
+        })
+}
+
+impl ComposeInvoker {
+    // Premier outil disponible dans l'ordre donné, l'ordre par défaut s'il est vide
+    pub fn detect(order: &[ComposeProvider]) -> Result<ComposeInvoker, String> {
+        detect_with(order, probe)
+    }
+
+    pub fn with_docker_host(mut self, docker_host: Option<String>) -> ComposeInvoker {
+        self.docker_host = docker_host;
+        self
+    }
+
+    // Commande compose dans le dossier de l'environnement, avec ses fichiers `-f`
+    pub fn command(&self) -> Result<Command, String> {
+        let mut command = Command::new(self.provider.program());
+        command
+            .args(self.provider.prefix_args())
+            .args(system::compose_file_args()?)
+            .current_dir(system::get_docker_compose_dir()?);
+        if let Some(docker_host) = &self.docker_host {
+            command.env("DOCKER_HOST", docker_host);
+        }
+        Ok(command)
+    }
+
+    pub fn run<I, S>(&self, args: I) -> Result<Output, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.command()?
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", self.provider.label(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection_follows_the_configured_order() {
+        let only_legacy_and_podman = |provider| match provider {
+            ComposeProvider::DockerPlugin => None,
+            ComposeProvider::DockerStandalone => Some("docker-compose version 1.29.2".to_string()),
+            ComposeProvider::Podman => Some("podman-compose version 1.0.6".to_string()),
+        };
+
+        let picked = detect_with(&[], only_legacy_and_podman).unwrap();
+        assert_eq!(picked.provider, ComposeProvider::DockerStandalone);
+        assert_eq!(picked.version, "docker-compose version 1.29.2");
+
+        let order = [ComposeProvider::Podman, ComposeProvider::DockerPlugin];
+        let picked = detect_with(&order, only_legacy_and_podman).unwrap();
+        assert_eq!(picked.provider, ComposeProvider::Podman);
+
+        assert_eq!(
+            detect_with(&[ComposeProvider::DockerPlugin], only_legacy_and_podman),
+            Err("No compose tool found. Install Docker Compose or podman-compose (tried: docker compose)".to_string())
+        );
+    }
+
+    #[test]
+    fn providers_serialize_as_their_command() {
+        let order = serde_json::to_string(&DEFAULT_ORDER).unwrap();
+        assert_eq!(
+            order,
+            r#"["docker compose","docker-compose","podman-compose"]"#
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::Service;
+
+// Graphe des dépendances entre services : rejette les dépendances inconnues,
+// les auto-dépendances et les cycles, et calcule l'ordre de démarrage
+
+fn visit<'a>(
+    name: &'a str,
+    services: &'a HashMap<String, Service>,
+    path: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+    order: &mut Vec<String>,
+) -> Result<(), String> {
+    if done.contains(name) {
+        return Ok(());
+    }
+    // Le service est déjà sur le chemin courant : on a bouclé
+    if let Some(start) = path.iter().position(|visited| *visited == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name);
+        return Err(format!("Dependency cycle detected: {}", cycle.join(" -> ")));
+    }
+
+    let service = services
+        .get(name)
+        .ok_or_else(|| format!("Dependency '{}' does not exist", name))?;
+
+    path.push(name);
+    for dependency in &service.dependencies {
+        if dependency == name {
+            return Err(format!("Service '{}' cannot depend on itself", name));
+        }
+        if !services.contains_key(dependency) {
+            return Err(format!(
+                "Dependency '{}' of service '{}' does not exist",
+                dependency, name
+            ));
+        }
+        visit(dependency, services, path, done, order)?;
+    }
+    path.pop();
+
+    done.insert(name);
+    order.push(name.to_string());
+    Ok(())
+}
+
+// Ordre de démarrage : chaque service apparaît après ses dépendances
+pub fn start_order(services: &HashMap<String, Service>) -> Result<Vec<String>, String> {
+    let mut names: Vec<&String> = services.keys().collect();
+    names.sort();
+
+    let mut done = HashSet::new();
+    let mut order = Vec::new();
+    for name in names {
+        visit(name, services, &mut Vec::new(), &mut done, &mut order)?;
+    }
+    Ok(order)
+}
+
+pub fn validate(services: &HashMap<String, Service>) -> Result<(), String> {
+    start_order(services).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn services(graph: &[(&str, &[&str])]) -> HashMap<String, Service> {
+        graph
+            .iter()
+            .map(|(name, dependencies)| {
+                let service = Service {
+                    name: name.to_string(),
+                    image: "alpine".to_string(),
+                    dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+                    ..Default::default()
+                };
+                (name.to_string(), service)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn start_order_puts_dependencies_first() {
+        let services = services(&[
+            ("app", &["cache", "db"]),
+            ("cache", &[]),
+            ("db", &["storage"]),
+            ("storage", &[]),
+        ]);
+
+        assert_eq!(
+            start_order(&services).unwrap(),
+            vec!["cache", "storage", "db", "app"]
+        );
+    }
+
+    #[test]
+    fn rejects_cycles_and_self_dependencies() {
+        let cycle = services(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"])]);
+        assert_eq!(
+            validate(&cycle).unwrap_err(),
+            "Dependency cycle detected: a -> b -> c -> a"
+        );
+
+        let own = services(&[("a", &["a"])]);
+        assert_eq!(
+            validate(&own).unwrap_err(),
+            "Service 'a' cannot depend on itself"
+        );
+
+        let missing = services(&[("a", &["ghost"])]);
+        assert_eq!(
+            validate(&missing).unwrap_err(),
+            "Dependency 'ghost' of service 'a' does not exist"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// Petit serveur DNS local : répond 127.0.0.1 / ::1 pour le domaine de base et ses
+// sous-domaines, refuse tout le reste. Il remplace les entrées du fichier hosts
+// quand systemd-resolved ou NetworkManager lui transmettent ce domaine.
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const TTL_SECONDS: u32 = 60;
+
+const RCODE_FORMERR: u8 = 1;
+const RCODE_NOTIMP: u8 = 4;
+const RCODE_REFUSED: u8 = 5;
+
+struct Question {
+    name: String,
+    qtype: u16,
+    qclass: u16,
+    // Fin de la question dans le paquet, pour la recopier telle quelle
+    end: usize,
+}
+
+fn read_u16(packet: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]))
+}
+
+// Les questions ne sont jamais compressées : un pointeur est traité comme invalide
+fn parse_question(packet: &[u8]) -> Option<Question> {
+    let mut at = 12;
+    let mut labels = Vec::new();
+    loop {
+        let length = *packet.get(at)? as usize;
+        at += 1;
+        if length == 0 {
+            break;
+        }
+        if length > 63 {
+            return None;
+        }
+        let label = packet.get(at..at + length)?;
+        labels.push(String::from_utf8_lossy(label).to_lowercase());
+        at += length;
+    }
+
+    Some(Question {
+        name: labels.join("."),
+        qtype: read_u16(packet, at)?,
+        qclass: read_u16(packet, at + 2)?,
+        end: at + 4,
+    })
+}
+
+fn in_domain(name: &str, base_domain: &str) -> bool {
+    name == base_domain
+        || name
+            .strip_suffix(base_domain)
+            .map(|prefix| prefix.ends_with('.'))
+            .unwrap_or(false)
+}
+
+fn response(query: &[u8], rcode: u8, question_end: Option<usize>) -> Vec<u8> {
+    let opcode_and_rd = query[2] & 0b0111_1001;
+    let mut packet = vec![
+        query[0],
+        query[1],
+        // QR + opcode + AA + RD, RA à 0 : pas de récursion
+        0b1000_0000 | opcode_and_rd | 0b0000_0100,
+        rcode,
+        0,
+        u8::from(question_end.is_some()),
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ];
+    if let Some(end) = question_end {
+        packet.extend_from_slice(&query[12..end]);
+    }
+    packet
+}
+
+// Réponse à une requête DNS, ou None si le paquet est trop court pour être répondu
+pub fn answer(query: &[u8], base_domain: &str) -> Option<Vec<u8>> {
+    if query.len() < 12 || query[2] & 0b1000_0000 != 0 {
+        return None;
+    }
+    let opcode = (query[2] >> 3) & 0b1111;
+    if opcode != 0 {
+        return Some(response(query, RCODE_NOTIMP, None));
+    }
+    if read_u16(query, 4) != Some(1) {
+        return Some(response(query, RCODE_FORMERR, None));
+    }
+    let question = match parse_question(query) {
+        Some(question) => question,
+        None => return Some(response(query, RCODE_FORMERR, None)),
+    };
+
+    if question.qclass != CLASS_IN || !in_domain(&question.name, base_domain) {
+        return Some(response(query, RCODE_REFUSED, Some(question.end)));
+    }
+
+    let mut packet = response(query, 0, Some(question.end));
+    let address: &[u8] = match question.qtype {
+        TYPE_A => &[127, 0, 0, 1],
+        TYPE_AAAA => &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+        // Nom connu mais type sans donnée (MX, TXT...) : réponse vide
+        _ => return Some(packet),
+    };
+
+    packet[7] = 1;
+    // Pointeur vers le nom de la question, à l'offset 12
+    packet.extend_from_slice(&[0xC0, 12]);
+    packet.extend_from_slice(&question.qtype.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet.extend_from_slice(&TTL_SECONDS.to_be_bytes());
+    packet.extend_from_slice(&(address.len() as u16).to_be_bytes());
+    packet.extend_from_slice(address);
+    Some(packet)
+}
+
+// Serveur UDP dans un thread, arrêté à la destruction ou par `stop`
+pub struct DnsServer {
+    address: SocketAddr,
+    base_domain: String,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DnsServer {
+    pub fn start(address: &str, base_domain: &str) -> Result<DnsServer, String> {
+        let socket = UdpSocket::bind(address)
+            .map_err(|e| format!("Failed to start DNS server on {}: {}", address, e))?;
+        // Le timeout permet au thread de voir la demande d'arrêt
+        socket
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .map_err(|e| format!("Failed to configure DNS server: {}", e))?;
+        let local_address = socket
+            .local_addr()
+            .map_err(|e| format!("Failed to start DNS server on {}: {}", address, e))?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+        let domain = base_domain.to_lowercase();
+        let handle = std::thread::spawn(move || {
+            let mut buffer = [0u8; 512];
+            while thread_running.load(Ordering::Relaxed) {
+                let (length, peer) = match socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(_) => continue,
+                };
+                if let Some(reply) = answer(&buffer[..length], &domain) {
+                    let _ = socket.send_to(&reply, peer);
+                }
+            }
+        });
+
+        Ok(DnsServer {
+            address: local_address,
+            base_domain: base_domain.to_string(),
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn base_domain(&self) -> &str {
+        &self.base_domain
+    }
+
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DnsServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn answers_only_the_base_domain() {
+        let a = answer(&query("Shop.Local.Test", TYPE_A), "local.test").unwrap();
+        assert_eq!(&a[..2], &[0x12, 0x34]);
+        assert_eq!(a[3] & 0x0F, 0);
+        assert_eq!(read_u16(&a, 6), Some(1));
+        assert_eq!(&a[a.len() - 4..], &[127, 0, 0, 1]);
+
+        let aaaa = answer(&query("api.shop.local.test", TYPE_AAAA), "local.test").unwrap();
+        assert_eq!(&aaaa[aaaa.len() - 2..], &[0, 1]);
+
+        let mx = answer(&query("local.test", 15), "local.test").unwrap();
+        assert_eq!((mx[3], read_u16(&mx, 6)), (0, Some(0)));
+
+        for name in ["example.com", "notlocal.test"] {
+            let refused = answer(&query(name, TYPE_A), "local.test").unwrap();
+            assert_eq!(refused[3] & 0x0F, RCODE_REFUSED);
+            assert_eq!(read_u16(&refused, 6), Some(0));
+        }
+    }
+
+    #[test]
+    fn serves_queries_over_udp() {
+        let server = DnsServer::start("127.0.0.1:0", "local.test").unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        client
+            .send_to(&query("shop.local.test", TYPE_A), server.address())
+            .unwrap();
+
+        let mut buffer = [0u8; 512];
+        let (length, _) = client.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[length - 4..length], &[127, 0, 0, 1]);
+    }
+}
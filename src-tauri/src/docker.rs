@@ -1,223 +1,418 @@
-use serde_yaml::to_string as yaml_to_string;
-use serde_yaml::{Mapping as YamlMap, Value as YamlValue};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::compose::{
+    ComposeFile, ComposeService, DependsOn, DependsOnCondition, Deploy, Healthcheck, Network,
+    ResourceLimits, Resources, Volume,
+};
+use crate::{
+    certs, dependencies, AppState, EnvTarget, Project, ResourceSettings, Service,
+    ServiceHealthcheck,
+};
+
+const NETWORK_NAME: &str = "dev_env_network";
+const NGINX_PORT: u16 = 80;
+const TRAEFIK_SERVICE: &str = "traefik";
+
+// Nom de l'image sans registre ni tag ("bitnami/mysql:8" -> "mysql")
+fn image_name(image: &str) -> &str {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    name.split(':').next().unwrap_or(name)
+}
 
-use crate::AppState;
+// Variables de connexion conventionnelles pour un service utilisé par un projet
+fn connection_environment(service: &Service) -> BTreeMap<String, String> {
+    let mut variables = BTreeMap::new();
+    let mut insert_from_config = |key: &str, config_key: &str| {
+        if let Some(value) = service.config.get(config_key) {
+            variables.insert(key.to_string(), value.clone());
+        }
+    };
+
+    match image_name(&service.image) {
+        "mysql" | "mariadb" => {
+            insert_from_config("DB_DATABASE", "MYSQL_DATABASE");
+            insert_from_config("DB_USERNAME", "MYSQL_USER");
+            insert_from_config("DB_PASSWORD", "MYSQL_PASSWORD");
+            variables.insert("DB_CONNECTION".to_string(), "mysql".to_string());
+            variables.insert("DB_HOST".to_string(), service.name.clone());
+            variables.insert("DB_PORT".to_string(), "3306".to_string());
+        }
+        "postgres" => {
+            insert_from_config("DB_DATABASE", "POSTGRES_DB");
+            insert_from_config("DB_USERNAME", "POSTGRES_USER");
+            insert_from_config("DB_PASSWORD", "POSTGRES_PASSWORD");
+            variables.insert("DB_CONNECTION".to_string(), "pgsql".to_string());
+            variables.insert("DB_HOST".to_string(), service.name.clone());
+            variables.insert("DB_PORT".to_string(), "5432".to_string());
+        }
+        "redis" => {
+            insert_from_config("REDIS_PASSWORD", "REDIS_PASSWORD");
+            variables.insert("REDIS_HOST".to_string(), service.name.clone());
+            variables.insert("REDIS_PORT".to_string(), "6379".to_string());
+        }
+        "mailhog" | "mailpit" => {
+            variables.insert("MAIL_HOST".to_string(), service.name.clone());
+            variables.insert("MAIL_PORT".to_string(), "1025".to_string());
+        }
+        _ => {}
+    }
 
-pub fn generate_docker_compose(state: &AppState) -> Result<String, String> {
-    let mut compose = YamlMap::new();
+    variables
+}
 
-    // Create services section
-    let mut services = YamlMap::new();
+// Variables du conteneur php : connexions aux services du projet, puis
+// variables du projet qui ont toujours la priorité
+fn php_environment(state: &AppState, project: &Project) -> BTreeMap<String, String> {
+    let mut variables = BTreeMap::new();
+    for service in project
+        .services
+        .iter()
+        .filter_map(|name| state.services.get(name))
+    {
+        variables.extend(connection_environment(service));
+    }
+    for (key, value) in project.environment_for(EnvTarget::Php) {
+        variables.insert(key.to_string(), value.to_string());
+    }
+    variables
+}
 
-    // Add Traefik as the proxy service
-    let mut traefik = YamlMap::new();
-    traefik.insert(YamlValue::from("image"), YamlValue::from("traefik:latest"));
-    traefik.insert(YamlValue::from("restart"), YamlValue::from("always"));
+// Labels Traefik d'un conteneur routé en HTTPS, namespacés par le nom du routeur
+fn traefik_labels(router: &str, hosts: &[String], port: u16) -> BTreeMap<String, String> {
+    let rule: Vec<String> = hosts
+        .iter()
+        .map(|host| format!("Host(`{}`)", host))
+        .collect();
+
+    let mut labels = BTreeMap::new();
+    labels.insert("traefik.enable".to_string(), "true".to_string());
+    labels.insert(
+        format!("traefik.http.routers.{}.rule", router),
+        rule.join(" || "),
+    );
+    labels.insert(
+        format!("traefik.http.routers.{}.entrypoints", router),
+        "websecure".to_string(),
+    );
+    labels.insert(
+        format!("traefik.http.routers.{}.tls", router),
+        "true".to_string(),
+    );
+    labels.insert(
+        format!("traefik.http.routers.{}.service", router),
+        router.to_string(),
+    );
+    labels.insert(
+        format!("traefik.http.services.{}.loadbalancer.server.port", router),
+        port.to_string(),
+    );
+    labels
+}
 
-    let mut traefik_ports = Vec::new();
-    traefik_ports.push(YamlValue::from("80:80"));
-    traefik_ports.push(YamlValue::from("443:443"));
-    traefik_ports.push(YamlValue::from("8080:8080"));
+// La socket du moteur (Docker ou Podman) est montée là où Traefik attend celle de Docker
+fn traefik_service(engine_socket: &Path) -> ComposeService {
+    ComposeService {
+        image: Some("traefik:latest".to_string()),
+        restart: Some("always".to_string()),
+        ports: vec![
+            "80:80".to_string(),
+            "443:443".to_string(),
+            "8080:8080".to_string(),
+        ],
+        volumes: vec![
+            format!("{}:/var/run/docker.sock", engine_socket.display()),
+            "./traefik/config:/etc/traefik".to_string(),
+            "./traefik/certs:/etc/certs".to_string(),
+        ],
+        networks: vec![NETWORK_NAME.to_string()],
+        ..Default::default()
+    }
+}
 
-    traefik.insert(YamlValue::from("ports"), YamlValue::from(traefik_ports));
+fn healthcheck(healthcheck: &ServiceHealthcheck) -> Healthcheck {
+    Healthcheck {
+        test: vec!["CMD-SHELL".to_string(), healthcheck.command.clone()],
+        interval: Some(healthcheck.interval.clone()),
+        retries: Some(healthcheck.retries),
+        start_period: Some(healthcheck.start_period.clone()),
+        timeout: None,
+    }
+}
 
-    let mut traefik_volumes = Vec::new();
-    traefik_volumes.push(YamlValue::from("/var/run/docker.sock:/var/run/docker.sock"));
-    traefik_volumes.push(YamlValue::from("./traefik/config:/etc/traefik"));
-    traefik_volumes.push(YamlValue::from("./traefik/certs:/etc/certs"));
+// Attendre qu'un service soit prêt quand il a un healthcheck, sinon seulement démarré
+fn depends_on(state: &AppState, dependencies: &[String]) -> DependsOn {
+    let has_healthcheck = |name: &String| {
+        state
+            .services
+            .get(name)
+            .is_some_and(|service| service.healthcheck.is_some())
+    };
+    if !dependencies.iter().any(has_healthcheck) {
+        return DependsOn::List(dependencies.to_vec());
+    }
 
-    traefik.insert(YamlValue::from("volumes"), YamlValue::from(traefik_volumes));
+    DependsOn::Conditions(
+        dependencies
+            .iter()
+            .map(|name| {
+                let condition = if has_healthcheck(name) {
+                    "service_healthy"
+                } else {
+                    "service_started"
+                };
+                (
+                    name.clone(),
+                    DependsOnCondition {
+                        condition: condition.to_string(),
+                    },
+                )
+            })
+            .collect(),
+    )
+}
 
-    // Add network configuration to traefik
-    let mut networks = Vec::new();
-    networks.push(YamlValue::from("dev_env_network"));
-    traefik.insert(YamlValue::from("networks"), YamlValue::from(networks.clone()));
+// docker-compose v1 ignore `deploy` sans --compatibility : les limites sont
+// aussi émises en `cpus` / `mem_limit`, avec les mêmes valeurs
+fn deploy(resources: &ResourceSettings) -> Option<Deploy> {
+    if resources.memory_limit.is_none() && resources.cpu_limit.is_none() {
+        return None;
+    }
 
-    services.insert(YamlValue::from("traefik"), YamlValue::from(traefik));
+    Some(Deploy {
+        resources: Some(Resources {
+            limits: Some(ResourceLimits {
+                cpus: resources.cpu_limit.clone(),
+                memory: resources.memory_limit.clone(),
+            }),
+            reservations: None,
+        }),
+    })
+}
 
-    // Add user services
-    for (name, service) in &state.services {
-        let mut service_config = YamlMap::new();
-        service_config.insert(
-            YamlValue::from("image"),
-            YamlValue::from(service.image.as_str()),
-        );
+fn restart(resources: &ResourceSettings) -> Option<String> {
+    resources
+        .restart
+        .map(|restart| restart.as_str().to_string())
+}
 
-        if !service.ports.is_empty() {
-            let ports: Vec<YamlValue> = service
-                .ports
-                .iter()
-                .map(|p| YamlValue::from(p.as_str()))
-                .collect();
-            service_config.insert(YamlValue::from("ports"), YamlValue::from(ports));
-        }
+fn user_service(state: &AppState, service: &Service) -> ComposeService {
+    ComposeService {
+        deploy: deploy(&service.resources),
+        cpus: service.resources.cpu_limit.clone(),
+        mem_limit: service.resources.memory_limit.clone(),
+        restart: restart(&service.resources),
+        image: Some(service.image.clone()),
+        ports: service.ports.clone(),
+        volumes: service.volumes.clone(),
+        depends_on: depends_on(state, &service.dependencies),
+        healthcheck: service.healthcheck.as_ref().map(healthcheck),
+        environment: service.config.clone().into_iter().collect(),
+        labels: service.labels.clone().into_iter().collect(),
+        networks: vec![NETWORK_NAME.to_string()],
+        ..Default::default()
+    }
+}
 
-        if !service.volumes.is_empty() {
-            let volumes: Vec<YamlValue> = service
-                .volumes
-                .iter()
-                .map(|v| YamlValue::from(v.as_str()))
-                .collect();
-            service_config.insert(YamlValue::from("volumes"), YamlValue::from(volumes));
-        }
+fn php_service(state: &AppState, project: &Project) -> ComposeService {
+    // Démarrer les services du projet avant php
+    let dependencies: Vec<String> = project
+        .services
+        .iter()
+        .filter(|name| state.services.contains_key(name.as_str()))
+        .cloned()
+        .collect();
+
+    ComposeService {
+        deploy: deploy(&project.resources),
+        cpus: project.resources.cpu_limit.clone(),
+        mem_limit: project.resources.memory_limit.clone(),
+        restart: restart(&project.resources),
+        image: Some(format!("php:{}-fpm", project.php_version)),
+        volumes: vec![format!("./projects/{}:/var/www/html", project.slug)],
+        environment: php_environment(state, project),
+        depends_on: depends_on(state, &dependencies),
+        networks: vec![NETWORK_NAME.to_string()],
+        ..Default::default()
+    }
+}
 
-        if !service.dependencies.is_empty() {
-            let depends_on: Vec<YamlValue> = service
-                .dependencies
-                .iter()
-                .map(|d| YamlValue::from(d.as_str()))
-                .collect();
-            service_config.insert(YamlValue::from("depends_on"), YamlValue::from(depends_on));
-        }
+fn nginx_service(project: &Project) -> ComposeService {
+    let service_name = format!("nginx_{}", project.slug);
+
+    ComposeService {
+        deploy: deploy(&project.resources),
+        cpus: project.resources.cpu_limit.clone(),
+        mem_limit: project.resources.memory_limit.clone(),
+        restart: restart(&project.resources),
+        image: Some("nginx:latest".to_string()),
+        volumes: vec![
+            format!("./projects/{}:/var/www/html", project.slug),
+            format!(
+                "./nginx/{}.conf:/etc/nginx/conf.d/default.conf",
+                project.slug
+            ),
+        ],
+        environment: project
+            .environment_for(EnvTarget::Nginx)
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        // Routeur et service Traefik propres au projet
+        labels: traefik_labels(&service_name, &project.hostnames(), NGINX_PORT),
+        depends_on: DependsOn::List(vec![format!("php_{}", project.slug)]),
+        networks: vec![NETWORK_NAME.to_string()],
+        ..Default::default()
+    }
+}
 
-        if !service.config.is_empty() {
-            let mut environment = YamlMap::new();
-            for (key, value) in &service.config {
-                environment.insert(
-                    YamlValue::from(key.as_str()),
-                    YamlValue::from(value.as_str()),
-                );
-            }
-            service_config.insert(YamlValue::from("environment"), YamlValue::from(environment));
-        }
+// Volume nommé d'une entrée `source:cible`, hors chemins relatifs ou absolus
+fn named_volume(volume: &str) -> Option<&str> {
+    let source = volume.split(':').next()?;
+    if source.is_empty() || source.starts_with('.') || source.starts_with('/') {
+        None
+    } else {
+        Some(source)
+    }
+}
 
-        // Add network configuration to the service
-        service_config.insert(YamlValue::from("networks"), YamlValue::from(networks.clone()));
+pub fn build_compose_file(state: &AppState) -> ComposeFile {
+    let mut compose = ComposeFile::default();
 
-        services.insert(
-            YamlValue::from(name.as_str()),
-            YamlValue::from(service_config),
-        );
-    }
+    // Add Traefik as the proxy service
+    compose.services.insert(
+        TRAEFIK_SERVICE.to_string(),
+        traefik_service(&state.settings.engine_socket),
+    );
 
-    // Add project environments
-    for (_, project) in &state.projects {
-        // Add PHP service
-        let php_service_name = format!("php_{}", project.slug);
-        let mut php_service = YamlMap::new();
-        php_service.insert(YamlValue::from("image"), YamlValue::from("php:8.2-fpm"));
-
-        let mut php_volumes = Vec::new();
-        php_volumes.push(YamlValue::from(format!(
-            "./projects/{}:/var/www/html",
-            project.slug
-        )));
-
-        php_service.insert(YamlValue::from("volumes"), YamlValue::from(php_volumes));
-
-        // Add network configuration to PHP service
-        php_service.insert(YamlValue::from("networks"), YamlValue::from(networks.clone()));
-
-        services.insert(
-            YamlValue::from(php_service_name.as_str()),
-            YamlValue::from(php_service),
-        );
+    // Add user services
+    for (name, service) in &state.services {
+        compose
+            .services
+            .insert(name.clone(), user_service(state, service));
 
-        // Add nginx service
-        let nginx_service_name = format!("nginx_{}", project.slug);
-        let mut nginx_service = YamlMap::new();
-        nginx_service.insert(YamlValue::from("image"), YamlValue::from("nginx:latest"));
-
-        let mut nginx_volumes = Vec::new();
-        nginx_volumes.push(YamlValue::from(format!(
-            "./projects/{}:/var/www/html",
-            project.slug
-        )));
-        nginx_volumes.push(YamlValue::from(format!(
-            "./nginx/{}.conf:/etc/nginx/conf.d/default.conf",
-            project.slug
-        )));
-
-        nginx_service.insert(YamlValue::from("volumes"), YamlValue::from(nginx_volumes));
-        
-        // Add network configuration to nginx service
-        nginx_service.insert(YamlValue::from("networks"), YamlValue::from(networks.clone()));
-
-        let mut nginx_labels = YamlMap::new();
-        nginx_labels.insert(YamlValue::from("traefik.enable"), YamlValue::from("true"));
-        nginx_labels.insert(
-            YamlValue::from("traefik.http.routers.nginx.rule"),
-            YamlValue::from(format!("Host(`{}`)", project.url.replace("https://", ""))),
-        );
-        nginx_labels.insert(
-            YamlValue::from("traefik.http.routers.nginx.tls"),
-            YamlValue::from("true"),
-        );
+        // Déclarer les volumes nommés utilisés par le service
+        for volume in service.volumes.iter().filter_map(|v| named_volume(v)) {
+            compose
+                .volumes
+                .insert(volume.to_string(), Volume::default());
+        }
+    }
 
-        nginx_service.insert(YamlValue::from("labels"), YamlValue::from(nginx_labels));
+    // Add project environments
+    for project in state.projects.values() {
+        compose
+            .services
+            .insert(format!("php_{}", project.slug), php_service(state, project));
+        compose
+            .services
+            .insert(format!("nginx_{}", project.slug), nginx_service(project));
+    }
 
-        let mut nginx_depends = Vec::new();
-        nginx_depends.push(YamlValue::from(php_service_name.as_str()));
+    compose.networks.insert(
+        NETWORK_NAME.to_string(),
+        Network {
+            driver: Some("bridge".to_string()),
+            ..Default::default()
+        },
+    );
 
-        nginx_service.insert(
-            YamlValue::from("depends_on"),
-            YamlValue::from(nginx_depends),
-        );
+    compose
+}
 
-        services.insert(
-            YamlValue::from(nginx_service_name.as_str()),
-            YamlValue::from(nginx_service),
-        );
-    }
+// Conteneurs d'un projet dans l'ordre de démarrage : Traefik, php, nginx
+// et les services du projet avec leurs dépendances
+pub fn project_containers(compose: &ComposeFile, project: &Project) -> Vec<String> {
+    let mut roots = vec![TRAEFIK_SERVICE.to_string()];
+    roots.extend(project.services.iter().cloned());
+    roots.push(format!("php_{}", project.slug));
+    roots.push(format!("nginx_{}", project.slug));
+    compose.with_dependencies(&roots)
+}
 
-    compose.insert(YamlValue::from("services"), YamlValue::from(services));
-
-    // Ajouter la configuration du réseau
-    let mut networks = YamlMap::new();
-    let mut dev_env_network = YamlMap::new();
-    dev_env_network.insert(YamlValue::from("driver"), YamlValue::from("bridge"));
-    networks.insert(YamlValue::from("dev_env_network"), YamlValue::from(dev_env_network));
-    compose.insert(YamlValue::from("networks"), YamlValue::from(networks));
-
-    // Ajouter la configuration des volumes
-    let mut volumes = YamlMap::new();
-    
-    // Parcourir tous les services pour trouver les volumes nommés
-    for (_, service) in &state.services {
-        for volume in &service.volumes {
-            let parts: Vec<&str> = volume.split(':').collect();
-            if let Some(volume_name) = parts.first() {
-                if !volume_name.starts_with('.') && !volume_name.starts_with('/') {
-                    // C'est un volume nommé, on l'ajoute à la configuration
-                    let volume_config = YamlMap::new();
-                    volumes.insert(
-                        YamlValue::from(volume_name.to_string()),
-                        YamlValue::from(volume_config),
-                    );
-                }
-            }
-        }
-    }
+// Conteneurs à arrêter avec un projet, dans l'ordre inverse du démarrage :
+// Traefik et les services encore utilisés par un autre projet restent démarrés
+pub fn project_exclusive_containers(
+    compose: &ComposeFile,
+    state: &AppState,
+    project: &Project,
+) -> Vec<String> {
+    let own = project_containers(compose, project);
+
+    // Restent démarrés : les autres projets et tous les services hors du projet
+    // (PhpMyAdmin, MailHog...), avec leurs dépendances directes ou transitives
+    let mut kept: Vec<String> = state
+        .projects
+        .values()
+        .filter(|other| other.slug != project.slug)
+        .flat_map(|other| project_containers(compose, other))
+        .collect();
+    kept.extend(
+        compose
+            .services
+            .keys()
+            .filter(|name| !own.contains(name))
+            .cloned(),
+    );
+    let shared: BTreeSet<String> = compose.with_dependencies(&kept).into_iter().collect();
+
+    own.into_iter()
+        .rev()
+        .filter(|container| container != TRAEFIK_SERVICE && !shared.contains(container))
+        .collect()
+}
 
-    // Ajouter les volumes à la configuration si présents
-    if !volumes.is_empty() {
-        compose.insert(YamlValue::from("volumes"), YamlValue::from(volumes));
-    }
+pub fn generate_docker_compose(state: &AppState) -> Result<String, String> {
+    // docker-compose refuse de démarrer un graphe de dépendances invalide
+    dependencies::validate(&state.services)?;
+    build_compose_file(state).to_yaml()
+}
 
-    // Convert to YAML
-    yaml_to_string(&YamlValue::from(compose))
-        .map_err(|e| format!("Failed to generate Docker Compose file: {}", e))
+// Certificats à émettre : un joker pour le domaine de base (outils communs),
+// puis un par nom servi par Traefik (projets, alias et services routés), pour que
+// l'inventaire vérifie exactement ce qui a été émis
+pub fn certificate_names(state: &AppState) -> Vec<Vec<String>> {
+    let base_domain = &state.settings.base_domain;
+    let mut names = vec![vec![format!("*.{}", base_domain), base_domain.clone()]];
+    names.extend(
+        served_hostnames(&build_compose_file(state))
+            .into_iter()
+            .filter(|hostname| hostname != base_domain)
+            .map(|hostname| vec![hostname]),
+    );
+    names
 }
 
-pub fn generate_traefik_config() -> Result<(), String> {
-    use crate::system;
-    use std::fs;
+// Noms d'hôte servis par Traefik, lus dans les règles `Host(...)` des labels
+pub fn served_hostnames(compose: &ComposeFile) -> Vec<String> {
+    let mut hostnames: Vec<String> = compose
+        .services
+        .values()
+        .flat_map(|service| service.labels.iter())
+        .filter(|(key, _)| key.starts_with("traefik.http.routers.") && key.ends_with(".rule"))
+        .flat_map(|(_, rule)| rule.split("Host(`").skip(1))
+        .filter_map(|host| host.split('`').next())
+        .map(|host| host.to_string())
+        .collect();
+    hostnames.sort();
+    hostnames.dedup();
+    hostnames
+}
 
-    let docker_dir = system::get_docker_compose_dir()?;
-    let traefik_dir = docker_dir.join("traefik");
-    let config_dir = traefik_dir.join("config");
-    let certs_dir = traefik_dir.join("certs");
+pub fn traefik_certs_dir() -> Result<std::path::PathBuf, String> {
+    Ok(crate::system::get_docker_compose_dir()?
+        .join("traefik")
+        .join("certs"))
+}
 
-    // Create directories if they don't exist
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create Traefik config directory: {}", e))?;
-    fs::create_dir_all(&certs_dir)
-        .map_err(|e| format!("Failed to create Traefik certs directory: {}", e))?;
+const FILE_PROVIDER: &str = r#"[providers.file]
+  directory = "/etc/traefik/dynamic"
+  watch = true
+"#;
 
-    // Create traefik.toml if it doesn't exist
-    let traefik_config_path = config_dir.join("traefik.toml");
-    if !traefik_config_path.exists() {
-        let traefik_config = r#"[api]
+fn static_traefik_config() -> String {
+    format!(
+        r#"[api]
   dashboard = true
   insecure = true
 
@@ -227,9 +422,10 @@ pub fn generate_traefik_config() -> Result<(), String> {
 [providers.docker]
   endpoint = "unix:///var/run/docker.sock"
   exposedByDefault = false
-  network = "dev_env_network"
+  network = "{}"
   watch = true
 
+{}
 [entryPoints.web]
   address = ":80"
   [entryPoints.web.http.redirections.entryPoint]
@@ -238,51 +434,368 @@ pub fn generate_traefik_config() -> Result<(), String> {
 
 [entryPoints.websecure]
   address = ":443"
+"#,
+        NETWORK_NAME, FILE_PROVIDER
+    )
+}
 
-# Configuration pour les certificats locaux
-[tls]
-  [[tls.certificates]]
-    certFile = "/etc/certs/local.test.crt"
-    keyFile = "/etc/certs/local.test.key"
-  
-  # Configuration pour les domaines en .local.test
-  [[tls.domains]]
-    main = "*.local.test"
-    sans = ["local.test"]
-"#;
-        fs::write(&traefik_config_path, traefik_config)
-            .map_err(|e| format!("Failed to write Traefik config: {}", e))?;
+// Une configuration créée par une version précédente ne charge pas encore le
+// dossier dynamique : seule cette section lui est ajoutée
+fn with_file_provider(existing: &str) -> Option<String> {
+    if existing.contains("[providers.file]") {
+        return None;
     }
+    let mut updated = existing.trim_end().to_string();
+    updated.push_str("\n\n");
+    updated.push_str(FILE_PROVIDER);
+    Some(updated)
+}
+
+pub fn generate_traefik_config(state: &AppState) -> Result<(), String> {
+    use crate::system;
+    use std::fs;
+
+    let docker_dir = system::get_docker_compose_dir()?;
+    let traefik_dir = docker_dir.join("traefik");
+    let config_dir = traefik_dir.join("config");
+    let dynamic_dir = config_dir.join("dynamic");
+    let certs_dir = traefik_dir.join("certs");
 
-    // Générer un certificat auto-signé pour *.local.test si nécessaire
-    let cert_path = certs_dir.join("local.test.crt");
-    let key_path = certs_dir.join("local.test.key");
-
-    if !cert_path.exists() || !key_path.exists() {
-        use std::process::Command;
-
-        Command::new("openssl")
-            .args([
-                "req",
-                "-x509",
-                "-newkey",
-                "rsa:4096",
-                "-sha256",
-                "-days",
-                "3650",
-                "-nodes",
-                "-keyout",
-                key_path.to_str().unwrap(),
-                "-out",
-                cert_path.to_str().unwrap(),
-                "-subj",
-                "/CN=*.local.test",
-                "-addext",
-                "subjectAltName=DNS:*.local.test,DNS:local.test",
-            ])
-            .output()
-            .map_err(|e| format!("Failed to generate SSL certificate: {}", e))?;
+    // Create directories if they don't exist
+    fs::create_dir_all(&dynamic_dir)
+        .map_err(|e| format!("Failed to create Traefik config directory: {}", e))?;
+    fs::create_dir_all(&certs_dir)
+        .map_err(|e| format!("Failed to create Traefik certs directory: {}", e))?;
+
+    // La configuration statique n'est créée qu'une fois : l'utilisateur peut la
+    // modifier (niveau de log, entrées...). Les certificats sont déclarés dans la
+    // configuration dynamique, relue par Traefik.
+    let traefik_config_path = config_dir.join("traefik.toml");
+    if let Ok(existing) = fs::read_to_string(&traefik_config_path) {
+        if let Some(updated) = with_file_provider(&existing) {
+            fs::write(&traefik_config_path, updated)
+                .map_err(|e| format!("Failed to write Traefik config: {}", e))?;
+        }
+    } else {
+        fs::write(&traefik_config_path, static_traefik_config())
+            .map_err(|e| format!("Failed to write Traefik config: {}", e))?;
     }
 
+    // Certificats signés par la CA locale pour chaque nom servi
+    let ca = certs::load_or_create_ca(&certs::ca_dir()?)?;
+    let records = certs::issue_certificates(&ca, &certs_dir, &certificate_names(state))?;
+    fs::write(
+        dynamic_dir.join("certificates.toml"),
+        certs::traefik_tls_config(&records, "/etc/certs"),
+    )
+    .map_err(|e| format!("Failed to write Traefik TLS config: {}", e))?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RestartPolicy;
+    use std::collections::HashMap;
+
+    fn project_with_environment() -> Project {
+        let mut environment = HashMap::new();
+        environment.insert("APP_ENV".to_string(), "dev".to_string());
+        environment.insert("DB_NAME".to_string(), "shop".to_string());
+        environment.insert("NGINX_HOST".to_string(), "shop.local.test".to_string());
+
+        let mut environment_targets = HashMap::new();
+        environment_targets.insert("APP_ENV".to_string(), EnvTarget::Both);
+        environment_targets.insert("NGINX_HOST".to_string(), EnvTarget::Nginx);
+
+        Project {
+            name: "Shop".to_string(),
+            slug: "shop".to_string(),
+            services: vec![],
+            url: "https://shop.local.test".to_string(),
+            environment,
+            environment_targets,
+            php_version: "8.3".to_string(),
+            resources: ResourceSettings::default(),
+            aliases: vec![],
+        }
+    }
+
+    fn mysql_service() -> Service {
+        let mut config = HashMap::new();
+        config.insert("MYSQL_DATABASE".to_string(), "dev_db".to_string());
+        config.insert("MYSQL_USER".to_string(), "dev_user".to_string());
+        config.insert("MYSQL_PASSWORD".to_string(), "dev_password".to_string());
+
+        Service {
+            name: "mysql8".to_string(),
+            image: "mysql:8".to_string(),
+            ports: vec![],
+            volumes: vec![],
+            global: true,
+            dependencies: vec![],
+            config,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn project_services_wire_php_container() {
+        let mut state = AppState::default();
+        let service = mysql_service();
+        state.services.insert(service.name.clone(), service);
+
+        let mut project = project_with_environment();
+        project.services.push("mysql8".to_string());
+        project
+            .environment
+            .insert("DB_PASSWORD".to_string(), "override".to_string());
+
+        let environment = php_environment(&state, &project);
+        assert_eq!(environment["DB_HOST"], "mysql8");
+        assert_eq!(environment["DB_PORT"], "3306");
+        assert_eq!(environment["DB_USERNAME"], "dev_user");
+        assert_eq!(environment["DB_PASSWORD"], "override");
+        assert!(!environment.contains_key("NGINX_HOST"));
+
+        state.projects.insert(project.slug.clone(), project);
+        let compose = build_compose_file(&state);
+        assert_eq!(
+            compose.services["php_shop"].depends_on.services(),
+            vec!["mysql8"]
+        );
+    }
+
+    #[test]
+    fn dependencies_wait_for_healthy_services() {
+        let mut state = AppState::default();
+        let mut mysql = mysql_service();
+        mysql.healthcheck = Some(ServiceHealthcheck::new("mysqladmin ping"));
+        state.services.insert(mysql.name.clone(), mysql);
+        let mut redis = mysql_service();
+        redis.name = "redis".to_string();
+        redis.image = "redis:latest".to_string();
+        state.services.insert(redis.name.clone(), redis);
+
+        let mut project = project_with_environment();
+        project.services = vec!["mysql8".to_string(), "redis".to_string()];
+        state.projects.insert(project.slug.clone(), project);
+
+        let compose = build_compose_file(&state);
+        match &compose.services["php_shop"].depends_on {
+            DependsOn::Conditions(conditions) => {
+                assert_eq!(conditions["mysql8"].condition, "service_healthy");
+                assert_eq!(conditions["redis"].condition, "service_started");
+            }
+            other => panic!("expected conditions, got {:?}", other),
+        }
+        assert_eq!(
+            compose.services["mysql8"]
+                .healthcheck
+                .as_ref()
+                .unwrap()
+                .test,
+            vec!["CMD-SHELL", "mysqladmin ping"]
+        );
+    }
+
+    #[test]
+    fn resources_are_emitted_for_services_and_projects() {
+        let mut state = AppState::default();
+        let mut mysql = mysql_service();
+        mysql.resources = ResourceSettings {
+            memory_limit: Some("1g".to_string()),
+            cpu_limit: Some("1.5".to_string()),
+            restart: Some(RestartPolicy::UnlessStopped),
+        };
+        state.services.insert(mysql.name.clone(), mysql);
+
+        let mut project = project_with_environment();
+        project.resources.memory_limit = Some("256m".to_string());
+        state.projects.insert(project.slug.clone(), project);
+
+        let compose = build_compose_file(&state);
+        let mysql = &compose.services["mysql8"];
+        let limits = mysql.deploy.as_ref().unwrap().resources.as_ref().unwrap();
+        let limits = limits.limits.as_ref().unwrap();
+        assert_eq!(limits.memory.as_deref(), Some("1g"));
+        assert_eq!(limits.cpus.as_deref(), Some("1.5"));
+        assert_eq!(mysql.restart.as_deref(), Some("unless-stopped"));
+        assert_eq!(mysql.mem_limit.as_deref(), Some("1g"));
+        assert_eq!(mysql.cpus.as_deref(), Some("1.5"));
+
+        for name in ["php_shop", "nginx_shop"] {
+            let service = &compose.services[name];
+            let limits = service.deploy.as_ref().unwrap().resources.as_ref().unwrap();
+            let limits = limits.limits.as_ref().unwrap();
+            assert_eq!(limits.memory.as_deref(), Some("256m"));
+            assert_eq!(limits.cpus, None);
+            assert_eq!(service.restart, None);
+        }
+    }
+
+    #[test]
+    fn service_labels_are_not_emitted_as_environment() {
+        let mut state = AppState::default();
+        let mut service = mysql_service();
+        service.name = "mailhog".to_string();
+        service
+            .labels
+            .insert("traefik.enable".to_string(), "true".to_string());
+        state.services.insert(service.name.clone(), service);
+
+        let compose = build_compose_file(&state);
+        let mailhog = &compose.services["mailhog"];
+        assert_eq!(mailhog.labels["traefik.enable"], "true");
+        assert!(!mailhog.environment.contains_key("traefik.enable"));
+    }
+
+    #[test]
+    fn project_containers_leave_other_projects_alone() {
+        let mut state = AppState::default();
+        for name in ["mysql8", "redis", "mailhog"] {
+            let mut service = mysql_service();
+            service.name = name.to_string();
+            state.services.insert(name.to_string(), service);
+        }
+        for (name, services) in [("shop", vec!["redis", "mysql8"]), ("blog", vec!["mysql8"])] {
+            let mut project = project_with_environment();
+            project.slug = name.to_string();
+            project.services = services.into_iter().map(String::from).collect();
+            state.projects.insert(project.slug.clone(), project);
+        }
+
+        let compose = build_compose_file(&state);
+        let shop = &state.projects["shop"];
+        assert_eq!(
+            project_containers(&compose, shop),
+            vec!["traefik", "redis", "mysql8", "php_shop", "nginx_shop"]
+        );
+        assert_eq!(
+            project_exclusive_containers(&compose, &state, shop),
+            vec!["nginx_shop", "php_shop", "redis"]
+        );
+    }
+
+    #[test]
+    fn services_needed_by_global_services_keep_running() {
+        let mut state = AppState::default();
+        for name in ["mysql8", "redis", "phpmyadmin"] {
+            let mut service = mysql_service();
+            service.name = name.to_string();
+            state.services.insert(name.to_string(), service);
+        }
+        state.services.get_mut("phpmyadmin").unwrap().dependencies = vec!["mysql8".to_string()];
+        let mut project = project_with_environment();
+        project.services = vec!["redis".to_string(), "mysql8".to_string()];
+        state.projects.insert(project.slug.clone(), project);
+
+        let compose = build_compose_file(&state);
+        assert_eq!(
+            project_exclusive_containers(&compose, &state, &state.projects["shop"]),
+            vec!["nginx_shop", "php_shop", "redis"]
+        );
+    }
+
+    #[test]
+    fn aliases_flow_into_rules_and_certificate() {
+        let mut state = AppState::default();
+        state.settings.base_domain = "client.test".to_string();
+        let mut project = project_with_environment();
+        project.url = "https://shop.client.test".to_string();
+        project.aliases = vec!["client.test".to_string(), "shop.example.test".to_string()];
+        state.projects.insert(project.slug.clone(), project);
+
+        let compose = build_compose_file(&state);
+        assert_eq!(
+            compose.services["nginx_shop"].labels["traefik.http.routers.nginx_shop.rule"],
+            "Host(`shop.client.test`) || Host(`client.test`) || Host(`shop.example.test`)"
+        );
+        assert_eq!(
+            served_hostnames(&compose),
+            vec!["client.test", "shop.client.test", "shop.example.test"]
+        );
+        assert_eq!(
+            certificate_names(&state),
+            vec![
+                vec!["*.client.test", "client.test"],
+                vec!["shop.client.test"],
+                vec!["shop.example.test"],
+            ]
+        );
+    }
+
+    #[test]
+    fn existing_traefik_config_only_gains_the_file_provider() {
+        assert_eq!(with_file_provider(&static_traefik_config()), None);
+
+        let edited = "[log]\n  level = \"DEBUG\"\n";
+        assert_eq!(
+            with_file_provider(edited).unwrap(),
+            format!("[log]\n  level = \"DEBUG\"\n\n{}", FILE_PROVIDER)
+        );
+    }
+
+    #[test]
+    fn traefik_labels_are_unique_per_project() {
+        let mut state = AppState::default();
+        for name in ["shop", "blog", "api-v2"] {
+            let mut project = project_with_environment();
+            project.name = name.to_string();
+            project.slug = name.to_string();
+            project.url = format!("https://{}.local.test", name);
+            state.projects.insert(project.slug.clone(), project);
+        }
+
+        let compose = build_compose_file(&state);
+
+        let mut seen_labels = std::collections::HashSet::new();
+        for name in ["shop", "blog", "api-v2"] {
+            let router = format!("nginx_{}", name);
+            let labels = &compose.services[&router].labels;
+
+            assert_eq!(
+                labels[&format!("traefik.http.routers.{}.rule", router)],
+                format!("Host(`{}.local.test`)", name)
+            );
+            assert_eq!(
+                labels[&format!("traefik.http.routers.{}.service", router)],
+                router
+            );
+            let port_label = format!("traefik.http.services.{}.loadbalancer.server.port", router);
+            assert_eq!(labels[&port_label], "80");
+
+            for key in labels.keys() {
+                if key != "traefik.enable" {
+                    assert!(
+                        seen_labels.insert(key.to_string()),
+                        "duplicate label {}",
+                        key
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn project_environment_matches_golden_file() {
+        let mut state = AppState::default();
+        state.settings.engine_socket = "/var/run/docker.sock".into();
+        let project = project_with_environment();
+        state.projects.insert(project.slug.clone(), project);
+
+        let compose = generate_docker_compose(&state).unwrap();
+        assert_eq!(
+            compose,
+            include_str!("../tests/golden/project_environment.yml")
+        );
+
+        state.settings.engine_socket = "/run/user/1000/podman/podman.sock".into();
+        let compose = build_compose_file(&state);
+        assert_eq!(
+            compose.services[TRAEFIK_SERVICE].volumes[0],
+            "/run/user/1000/podman/podman.sock:/var/run/docker.sock"
+        );
+    }
+}
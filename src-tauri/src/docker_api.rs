@@ -0,0 +1,647 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Client minimal de l'API Docker Engine : HTTP/1.1 sur la socket unix du démon
+// (ou l'adresse TCP de DOCKER_HOST), une connexion par requête.
+
+const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Endpoint {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl Endpoint {
+    // DOCKER_HOST accepte unix:///chemin ou tcp://hôte:port
+    pub fn parse(docker_host: &str) -> Result<Endpoint, String> {
+        if let Some(path) = docker_host.strip_prefix("unix://") {
+            Ok(Endpoint::Unix(PathBuf::from(path)))
+        } else if let Some(address) = docker_host.strip_prefix("tcp://") {
+            Ok(Endpoint::Tcp(address.trim_end_matches('/').to_string()))
+        } else {
+            Err(format!("Unsupported DOCKER_HOST: {}", docker_host))
+        }
+    }
+
+    pub fn from_env() -> Result<Endpoint, String> {
+        match std::env::var("DOCKER_HOST") {
+            Ok(host) if !host.is_empty() => Endpoint::parse(&host),
+            _ => Ok(Endpoint::Unix(PathBuf::from(DEFAULT_SOCKET))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct DockerVersion {
+    pub version: String,
+    pub api_version: String,
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub arch: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerSummary {
+    pub id: String,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub ports: Vec<ContainerPort>,
+}
+
+// Port d'un conteneur ; public_port est absent s'il n'est pas publié sur l'hôte
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerPort {
+    #[serde(default)]
+    pub private_port: u16,
+    #[serde(default)]
+    pub public_port: Option<u16>,
+    #[serde(rename = "Type", default)]
+    pub protocol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerState {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub running: bool,
+    #[serde(default)]
+    pub exit_code: i64,
+    #[serde(default)]
+    pub started_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerConfig {
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerDetails {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub state: ContainerState,
+    #[serde(default)]
+    pub config: ContainerConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct EventActor {
+    #[serde(rename = "ID", default)]
+    pub id: String,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DockerEvent {
+    #[serde(rename = "Type", default)]
+    pub kind: String,
+    #[serde(rename = "Action", default)]
+    pub action: String,
+    #[serde(rename = "Actor", default)]
+    pub actor: EventActor,
+    #[serde(default)]
+    pub time: i64,
+}
+
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+trait Connection: Read + Write {}
+impl<T: Read + Write> Connection for T {}
+
+pub struct DockerClient {
+    endpoint: Endpoint,
+}
+
+impl DockerClient {
+    pub fn new(endpoint: Endpoint) -> DockerClient {
+        DockerClient { endpoint }
+    }
+
+    pub fn from_env() -> Result<DockerClient, String> {
+        Ok(DockerClient::new(Endpoint::from_env()?))
+    }
+
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    // Toutes les étapes sont bornées : un démon figé ou injoignable ne bloque pas l'appelant
+    fn connect(&self) -> Result<Box<dyn Connection>, String> {
+        let configure_error =
+            |e: std::io::Error| format!("Failed to configure Docker connection: {}", e);
+        match &self.endpoint {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let stream = UnixStream::connect(path).map_err(|e| {
+                    format!("Failed to connect to Docker at {}: {}", path.display(), e)
+                })?;
+                stream
+                    .set_read_timeout(Some(TIMEOUT))
+                    .map_err(configure_error)?;
+                stream
+                    .set_write_timeout(Some(TIMEOUT))
+                    .map_err(configure_error)?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(path) => Err(format!(
+                "Unix sockets are not supported on this platform: {}",
+                path.display()
+            )),
+            Endpoint::Tcp(address) => {
+                let connect_error = |e: std::io::Error| {
+                    format!("Failed to connect to Docker at {}: {}", address, e)
+                };
+                // Première adresse résolue qui répond, comme TcpStream::connect
+                let mut last_error =
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "no address resolved");
+                let stream = address
+                    .to_socket_addrs()
+                    .map_err(connect_error)?
+                    .find_map(|socket_address| {
+                        TcpStream::connect_timeout(&socket_address, TIMEOUT)
+                            .map_err(|e| last_error = e)
+                            .ok()
+                    })
+                    .ok_or_else(|| connect_error(last_error))?;
+                stream
+                    .set_read_timeout(Some(TIMEOUT))
+                    .map_err(configure_error)?;
+                stream
+                    .set_write_timeout(Some(TIMEOUT))
+                    .map_err(configure_error)?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<Response, String> {
+        let mut stream = self.connect()?;
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: docker\r\nUser-Agent: dev-env\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("Failed to send Docker API request: {}", e))?;
+
+        let mut raw = Vec::new();
+        stream
+            .read_to_end(&mut raw)
+            .map_err(|e| format!("Failed to read Docker API response: {}", e))?;
+        parse_response(&raw)
+    }
+
+    // Corps de la réponse, en erreur si le statut n'est pas 2xx
+    fn get_ok(&self, path: &str) -> Result<Vec<u8>, String> {
+        let response = self.get(path)?;
+        if (200..300).contains(&response.status) {
+            Ok(response.body)
+        } else {
+            Err(format!(
+                "Docker API error {} on {}: {}",
+                response.status,
+                path,
+                error_message(&response.body)
+            ))
+        }
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        let body = self.get_ok(path)?;
+        serde_json::from_slice(&body)
+            .map_err(|e| format!("Failed to parse Docker API response: {}", e))
+    }
+
+    pub fn ping(&self) -> Result<(), String> {
+        self.get_ok("/_ping").map(|_| ())
+    }
+
+    pub fn version(&self) -> Result<DockerVersion, String> {
+        self.get_json("/version")
+    }
+
+    // Conteneurs, filtrés par labels "clé=valeur" ; `all` inclut les conteneurs arrêtés
+    pub fn containers(
+        &self,
+        all: bool,
+        labels: &[String],
+    ) -> Result<Vec<ContainerSummary>, String> {
+        let mut path = format!("/containers/json?all={}", all);
+        if !labels.is_empty() {
+            let filters = serde_json::json!({ "label": labels });
+            path.push_str("&filters=");
+            path.push_str(&percent_encode(&filters.to_string()));
+        }
+        self.get_json(&path)
+    }
+
+    pub fn inspect(&self, id: &str) -> Result<ContainerDetails, String> {
+        self.get_json(&format!("/containers/{}/json", percent_encode(id)))
+    }
+
+    // Dernières lignes de stdout et stderr, sans les en-têtes de multiplexage
+    pub fn logs(&self, id: &str, tail: usize) -> Result<String, String> {
+        let body = self.get_ok(&format!(
+            "/containers/{}/logs?stdout=true&stderr=true&tail={}",
+            percent_encode(id),
+            tail
+        ))?;
+        Ok(String::from_utf8_lossy(&demultiplex(&body)).into_owned())
+    }
+
+    // Événements sur une période bornée (timestamps unix), le flux se termine à `until`
+    pub fn events(&self, since: i64, until: i64) -> Result<Vec<DockerEvent>, String> {
+        let body = self.get_ok(&format!("/events?since={}&until={}", since, until))?;
+        String::from_utf8_lossy(&body)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("Failed to parse Docker event: {}", e))
+            })
+            .collect()
+    }
+}
+
+fn error_message(body: &[u8]) -> String {
+    serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string())
+}
+
+fn percent_encode(input: &str) -> String {
+    let mut encoded = String::new();
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response, String> {
+    let header_end = find(raw, b"\r\n\r\n")
+        .ok_or_else(|| "Invalid Docker API response: missing headers".to_string())?;
+    let head = String::from_utf8_lossy(&raw[..header_end]);
+    let mut lines = head.split("\r\n");
+
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| "Invalid Docker API response: bad status line".to_string())?;
+
+    let mut chunked = false;
+    let mut content_length = None;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                "content-length" => content_length = value.parse::<usize>().ok(),
+                _ => {}
+            }
+        }
+    }
+
+    let body = &raw[header_end + 4..];
+    let body = if chunked {
+        decode_chunked(body)?
+    } else if let Some(length) = content_length {
+        body[..length.min(body.len())].to_vec()
+    } else {
+        body.to_vec()
+    };
+    Ok(Response { status, body })
+}
+
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = find(body, b"\r\n")
+            .ok_or_else(|| "Invalid Docker API response: truncated chunk".to_string())?;
+        let size_line = String::from_utf8_lossy(&body[..line_end]);
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| format!("Invalid Docker API response: bad chunk size '{}'", size_hex))?;
+        if size == 0 {
+            return Ok(decoded);
+        }
+        let start = line_end + 2;
+        let chunk = body
+            .get(start..start + size)
+            .ok_or_else(|| "Invalid Docker API response: truncated chunk".to_string())?;
+        decoded.extend_from_slice(chunk);
+        body = body.get(start + size + 2..).unwrap_or(&[]);
+    }
+}
+
+// Sans TTY, les logs sont découpés en trames : 1 octet de flux, 3 de remplissage,
+// 4 de longueur big-endian. Un conteneur avec TTY renvoie le texte brut.
+fn demultiplex(body: &[u8]) -> Vec<u8> {
+    let framed = body.len() >= 8 && body[0] <= 2 && body[1..4] == [0, 0, 0];
+    if !framed {
+        return body.to_vec();
+    }
+
+    let mut output = Vec::new();
+    let mut at = 0;
+    while at + 8 <= body.len() {
+        let length =
+            u32::from_be_bytes([body[at + 4], body[at + 5], body[at + 6], body[at + 7]]) as usize;
+        let end = (at + 8 + length).min(body.len());
+        output.extend_from_slice(&body[at + 8..end]);
+        at = end;
+    }
+    output
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::os::unix::net::UnixListener;
+    use std::sync::mpsc;
+
+    // Faux démon : répond aux requêtes dans l'ordre avec les réponses brutes données
+    // et renvoie les lignes de requête reçues
+    fn fake_daemon(name: &str, responses: Vec<Vec<u8>>) -> (DockerClient, mpsc::Receiver<String>) {
+        let responses = responses
+            .into_iter()
+            .map(|response| vec![response])
+            .collect();
+        fake_daemon_in_parts(name, responses)
+    }
+
+    // Chaque réponse est envoyée en plusieurs écritures espacées, que le client
+    // reçoit en plusieurs lectures
+    fn fake_daemon_in_parts(
+        name: &str,
+        responses: Vec<Vec<Vec<u8>>>,
+    ) -> (DockerClient, mpsc::Receiver<String>) {
+        let dir = std::env::temp_dir().join(format!(
+            "dev-env-docker-api-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("docker.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header == "\r\n" || header.is_empty() {
+                        break;
+                    }
+                }
+                sender.send(request_line.trim().to_string()).unwrap();
+                for part in response {
+                    reader.get_mut().write_all(&part).unwrap();
+                    reader.get_mut().flush().unwrap();
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                }
+            }
+        });
+
+        (DockerClient::new(Endpoint::Unix(socket)), receiver)
+    }
+
+    fn http(status: &str, body: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn endpoint_follows_docker_host() {
+        assert_eq!(
+            Endpoint::parse("unix:///run/user/1000/docker.sock"),
+            Ok(Endpoint::Unix(PathBuf::from("/run/user/1000/docker.sock")))
+        );
+        assert_eq!(
+            Endpoint::parse("tcp://127.0.0.1:2375"),
+            Ok(Endpoint::Tcp("127.0.0.1:2375".to_string()))
+        );
+        assert!(Endpoint::parse("ssh://host").is_err());
+    }
+
+    #[test]
+    fn pings_and_reads_version() {
+        let (client, requests) = fake_daemon(
+            "version",
+            vec![
+                http("200 OK", "OK"),
+                http(
+                    "200 OK",
+                    r#"{"Version":"27.3.1","ApiVersion":"1.47","Os":"linux","Arch":"amd64"}"#,
+                ),
+                http(
+                    "500 Internal Server Error",
+                    r#"{"message":"daemon is down"}"#,
+                ),
+            ],
+        );
+
+        client.ping().unwrap();
+        let version = client.version().unwrap();
+        assert_eq!(
+            (version.version.as_str(), version.api_version.as_str()),
+            ("27.3.1", "1.47")
+        );
+        assert_eq!(
+            client.ping(),
+            Err("Docker API error 500 on /_ping: daemon is down".to_string())
+        );
+
+        assert_eq!(requests.recv().unwrap(), "GET /_ping HTTP/1.1");
+        assert_eq!(requests.recv().unwrap(), "GET /version HTTP/1.1");
+    }
+
+    #[test]
+    fn lists_containers_from_a_chunked_response() {
+        let body = r#"[{"Id":"abc","Names":["/traefik"],"Image":"traefik:v3","State":"running","Status":"Up 2 minutes","Labels":{"com.docker.compose.service":"traefik"}}]"#;
+        let (first, second) = body.split_at(40);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            first.len(),
+            first,
+            second.len(),
+            second
+        );
+        let (client, requests) = fake_daemon("containers", vec![response.into_bytes()]);
+
+        let containers = client
+            .containers(false, &["com.docker.compose.project=dev-env".to_string()])
+            .unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].names, vec!["/traefik".to_string()]);
+        assert_eq!(
+            containers[0].labels["com.docker.compose.service"],
+            "traefik"
+        );
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("GET /containers/json?all=false&filters=%7B%22label%22"));
+    }
+
+    #[test]
+    fn inspects_a_container() {
+        let (client, requests) = fake_daemon(
+            "inspect",
+            vec![
+                http(
+                    "200 OK",
+                    r#"{"Id":"abc","Name":"/dev-env-mysql-1","State":{"Status":"running","Running":true,"ExitCode":0,"StartedAt":"2024-05-01T10:00:00Z"},"Config":{"Image":"mysql:8.0","Labels":{"com.docker.compose.service":"mysql"}}}"#,
+                ),
+                http(
+                    "404 Not Found",
+                    r#"{"message":"No such container: dev env"}"#,
+                ),
+            ],
+        );
+
+        let details = client.inspect("dev-env-mysql-1").unwrap();
+        assert_eq!(details.name, "/dev-env-mysql-1");
+        assert!(details.state.running);
+        assert_eq!(details.state.status, "running");
+        assert_eq!(details.config.image, "mysql:8.0");
+        assert_eq!(details.config.labels["com.docker.compose.service"], "mysql");
+        assert_eq!(
+            client.inspect("dev env").unwrap_err(),
+            "Docker API error 404 on /containers/dev%20env/json: No such container: dev env"
+        );
+
+        assert_eq!(
+            requests.recv().unwrap(),
+            "GET /containers/dev-env-mysql-1/json HTTP/1.1"
+        );
+        assert_eq!(
+            requests.recv().unwrap(),
+            "GET /containers/dev%20env/json HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn reads_events_split_across_reads() {
+        let body = concat!(
+            r#"{"Type":"container","Action":"die","Actor":{"ID":"abc","Attributes":{"name":"dev-env-php-1","exitCode":"137"}},"time":1714557600}"#,
+            "\n",
+            r#"{"Type":"container","Action":"oom","Actor":{"ID":"abc","Attributes":{"name":"dev-env-php-1"}},"time":1714557601}"#,
+            "\n"
+        );
+        let (first, second) = body.split_at(60);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            first.len(),
+            first,
+            second.len(),
+            second
+        )
+        .into_bytes();
+        // Coupures au milieu d'un chunk et au milieu de la taille du suivant
+        let chunk_size_at =
+            find(&response, format!("\r\n{:x}\r\n", second.len()).as_bytes()).unwrap() + 3;
+        let parts = vec![
+            response[..80].to_vec(),
+            response[80..chunk_size_at].to_vec(),
+            response[chunk_size_at..].to_vec(),
+        ];
+        let (client, requests) = fake_daemon_in_parts("events", vec![parts]);
+
+        let events = client.events(1714557500, 1714557700).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            (events[0].kind.as_str(), events[0].action.as_str()),
+            ("container", "die")
+        );
+        assert_eq!(events[0].actor.id, "abc");
+        assert_eq!(events[0].actor.attributes["exitCode"], "137");
+        assert_eq!(events[1].action, "oom");
+        assert_eq!(events[1].time, 1714557601);
+
+        assert_eq!(
+            requests.recv().unwrap(),
+            "GET /events?since=1714557500&until=1714557700 HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn demultiplexes_logs() {
+        let mut body = Vec::new();
+        for (stream, text) in [(1u8, "started\n"), (2u8, "warning\n")] {
+            body.extend_from_slice(&[stream, 0, 0, 0]);
+            body.extend_from_slice(&(text.len() as u32).to_be_bytes());
+            body.extend_from_slice(text.as_bytes());
+        }
+        let mut response =
+            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        response.extend_from_slice(&body);
+        let (client, requests) = fake_daemon("logs", vec![response]);
+
+        assert_eq!(client.logs("php_shop", 50).unwrap(), "started\nwarning\n");
+        assert_eq!(
+            requests.recv().unwrap(),
+            "GET /containers/php_shop/logs?stdout=true&stderr=true&tail=50 HTTP/1.1"
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::compose::{ComposeFile, ComposeService, Healthcheck};
+use crate::{
+    AppState, Project, ResourceSettings, RestartPolicy, Service, ServiceHealthcheck,
+    SUPPORTED_PHP_VERSIONS,
+};
+
+// Résultat de l'analyse d'un docker-compose.yml existant : ce qui peut être
+// ajouté à l'état de dev-env et ce qui n'a pas pu être repris
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ComposeImport {
+    pub project: Project,
+    pub services: Vec<Service>,
+    pub warnings: Vec<String>,
+}
+
+enum ServiceRole {
+    Php(Option<String>),
+    WebServer,
+    Other,
+}
+
+fn image_tag(image: &str) -> Option<&str> {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    name.split_once(':').map(|(_, tag)| tag)
+}
+
+fn service_role(service: &ComposeService) -> ServiceRole {
+    let image = match &service.image {
+        Some(image) => image,
+        None => return ServiceRole::Other,
+    };
+    let name = image.rsplit('/').next().unwrap_or(image);
+    let name = name.split(':').next().unwrap_or(name);
+
+    match name {
+        // "8.3-fpm", "8.3-fpm-alpine"... -> "8.3"
+        "php" => ServiceRole::Php(
+            image_tag(image)
+                .and_then(|tag| tag.split('-').next())
+                .map(|version| version.to_string()),
+        ),
+        "nginx" | "httpd" | "caddy" => ServiceRole::WebServer,
+        _ => ServiceRole::Other,
+    }
+}
+
+// Les chemins relatifs sont relatifs au fichier importé, pas au dossier docker de dev-env
+fn absolute_volume(volume: &str, base_dir: &Path) -> String {
+    if let Some(relative) = volume.strip_prefix("./") {
+        base_dir.join(relative).display().to_string()
+    } else if volume.starts_with("..") {
+        base_dir.join(volume).display().to_string()
+    } else {
+        volume.to_string()
+    }
+}
+
+fn unmapped_keys(name: &str, service: &ComposeService, warnings: &mut Vec<String>) {
+    for key in service.extra.keys() {
+        warnings.push(format!("Service '{}': '{}' is not supported", name, key));
+    }
+    if service.container_name.is_some() {
+        warnings.push(format!("Service '{}': 'container_name' is ignored", name));
+    }
+}
+
+// Le conteneur php du projet est généré : seuls l'image et l'environnement
+// du service importé sont repris
+fn dropped_php_settings(name: &str, service: &ComposeService, warnings: &mut Vec<String>) {
+    unmapped_keys(name, service, warnings);
+    let dropped = [
+        ("cpus", service.cpus.is_some()),
+        ("depends_on", !service.depends_on.is_empty()),
+        ("deploy", service.deploy.is_some()),
+        ("healthcheck", service.healthcheck.is_some()),
+        ("labels", !service.labels.is_empty()),
+        ("mem_limit", service.mem_limit.is_some()),
+        ("networks", !service.networks.is_empty()),
+        ("ports", !service.ports.is_empty()),
+        ("restart", service.restart.is_some()),
+        ("volumes", !service.volumes.is_empty()),
+    ];
+    for (key, _) in dropped.iter().filter(|(_, present)| *present) {
+        warnings.push(format!(
+            "Service '{}': '{}' is not applied to the project's PHP container",
+            name, key
+        ));
+    }
+}
+
+// Seules les commandes CMD et CMD-SHELL ont un équivalent dans dev-env
+fn import_healthcheck(
+    name: &str,
+    healthcheck: &Healthcheck,
+    warnings: &mut Vec<String>,
+) -> Option<ServiceHealthcheck> {
+    let command = match healthcheck.test.split_first() {
+        Some((kind, command)) if kind == "CMD-SHELL" || kind == "CMD" => command.join(" "),
+        _ => {
+            warnings.push(format!(
+                "Service '{}': healthcheck test is not supported",
+                name
+            ));
+            return None;
+        }
+    };
+
+    let mut imported = ServiceHealthcheck::new(&command);
+    if let Some(interval) = &healthcheck.interval {
+        imported.interval = interval.clone();
+    }
+    if let Some(retries) = healthcheck.retries {
+        imported.retries = retries;
+    }
+    if let Some(start_period) = &healthcheck.start_period {
+        imported.start_period = start_period.clone();
+    }
+    Some(imported)
+}
+
+// Seules les limites `deploy.resources.limits` et `restart` sont reprises
+fn import_resources(
+    name: &str,
+    service: &ComposeService,
+    warnings: &mut Vec<String>,
+) -> ResourceSettings {
+    let mut resources = ResourceSettings::default();
+    if let Some(deploy) = &service.deploy {
+        let limits = deploy.resources.as_ref().and_then(|r| r.limits.as_ref());
+        if let Some(limits) = limits {
+            resources.memory_limit = limits.memory.clone();
+            resources.cpu_limit = limits.cpus.clone();
+        }
+        let reservations = deploy
+            .resources
+            .as_ref()
+            .and_then(|r| r.reservations.as_ref());
+        if reservations.is_some() {
+            warnings.push(format!(
+                "Service '{}': resource reservations are not supported",
+                name
+            ));
+        }
+    }
+    if let Some(restart) = &service.restart {
+        resources.restart = RestartPolicy::parse(restart);
+        if resources.restart.is_none() {
+            warnings.push(format!(
+                "Service '{}': restart policy '{}' is not supported",
+                name, restart
+            ));
+        }
+    }
+    if resources.validate().is_err() {
+        warnings.push(format!(
+            "Service '{}': resource limits are invalid and were dropped",
+            name
+        ));
+        resources.memory_limit = None;
+        resources.cpu_limit = None;
+    }
+    resources
+}
+
+pub fn import_compose(
+    compose: &ComposeFile,
+    project_name: &str,
+    base_dir: &Path,
+    state: &AppState,
+) -> ComposeImport {
+    let mut project = Project::new(project_name.to_string(), &state.settings.base_domain);
+    let mut services = Vec::new();
+    let mut warnings = Vec::new();
+    let mut skipped = HashSet::new();
+
+    for key in compose.extra.keys().filter(|key| key.as_str() != "version") {
+        warnings.push(format!("Top-level '{}' is not supported", key));
+    }
+    for network in compose.networks.keys() {
+        warnings.push(format!(
+            "Network '{}' is replaced by the dev-env network",
+            network
+        ));
+    }
+
+    for (name, service) in &compose.services {
+        match service_role(service) {
+            ServiceRole::Php(version) => {
+                // Le conteneur php du projet est généré par dev-env
+                match version {
+                    Some(version) if SUPPORTED_PHP_VERSIONS.contains(&version.as_str()) => {
+                        project.php_version = version;
+                    }
+                    _ => warnings.push(format!(
+                        "Service '{}': PHP version not supported, using {}",
+                        name, project.php_version
+                    )),
+                }
+                project.environment.extend(service.environment.clone());
+                dropped_php_settings(name, service, &mut warnings);
+                skipped.insert(name.clone());
+                continue;
+            }
+            ServiceRole::WebServer => {
+                warnings.push(format!(
+                    "Service '{}' is replaced by the project's nginx container",
+                    name
+                ));
+                skipped.insert(name.clone());
+                continue;
+            }
+            ServiceRole::Other => {}
+        }
+
+        let image = match &service.image {
+            Some(image) => image.clone(),
+            None => {
+                warnings.push(format!(
+                    "Service '{}' has no image and cannot be imported",
+                    name
+                ));
+                skipped.insert(name.clone());
+                continue;
+            }
+        };
+
+        if state.services.contains_key(name) {
+            warnings.push(format!(
+                "Service '{}' already exists and will not be imported",
+                name
+            ));
+            skipped.insert(name.clone());
+            project.services.push(name.clone());
+            continue;
+        }
+
+        unmapped_keys(name, service, &mut warnings);
+
+        services.push(Service {
+            name: name.clone(),
+            image,
+            ports: service.ports.clone(),
+            volumes: service
+                .volumes
+                .iter()
+                .map(|volume| absolute_volume(volume, base_dir))
+                .collect(),
+            global: false,
+            dependencies: service.depends_on.services(),
+            config: service.environment.clone().into_iter().collect(),
+            labels: service.labels.clone().into_iter().collect(),
+            healthcheck: service
+                .healthcheck
+                .as_ref()
+                .and_then(|healthcheck| import_healthcheck(name, healthcheck, &mut warnings)),
+            resources: import_resources(name, service, &mut warnings),
+        });
+        project.services.push(name.clone());
+    }
+
+    // Les dépendances vers des services non importés sont retirées
+    for service in &mut services {
+        let name = service.name.clone();
+        service.dependencies.retain(|dependency| {
+            let keep = !skipped.contains(dependency) || state.services.contains_key(dependency);
+            if !keep {
+                warnings.push(format!(
+                    "Service '{}': dependency on '{}' was dropped",
+                    name, dependency
+                ));
+            }
+            keep
+        });
+    }
+
+    ComposeImport {
+        project,
+        services,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_services_and_reports_unsupported_keys() {
+        let compose = ComposeFile::from_yaml(
+            r#"
+version: "3.8"
+services:
+  app:
+    image: php:8.1-fpm
+    build: ./docker/php
+    ports: ["9003:9003"]
+    volumes: ["./docker/php.ini:/usr/local/etc/php/php.ini"]
+    environment:
+      APP_ENV: local
+  web:
+    image: nginx:alpine
+    depends_on: [app]
+  db:
+    image: mariadb:11
+    command: --character-set-server=utf8mb4
+    ports: ["3307:3306"]
+    volumes: ["./docker/db:/var/lib/mysql", "db_data:/backup"]
+    environment:
+      MARIADB_DATABASE: shop
+  worker:
+    build: .
+    depends_on: [db, app]
+"#,
+        )
+        .unwrap();
+
+        let import = import_compose(
+            &compose,
+            "Shop",
+            Path::new("/srv/shop"),
+            &AppState::default(),
+        );
+
+        assert_eq!(import.project.slug, "shop");
+        assert_eq!(import.project.php_version, "8.1");
+        assert_eq!(import.project.environment["APP_ENV"], "local");
+        assert_eq!(import.project.services, vec!["db"]);
+
+        assert_eq!(import.services.len(), 1);
+        let db = &import.services[0];
+        assert_eq!(db.image, "mariadb:11");
+        assert_eq!(db.ports, vec!["3307:3306"]);
+        assert_eq!(
+            db.volumes,
+            vec!["/srv/shop/docker/db:/var/lib/mysql", "db_data:/backup"]
+        );
+        assert_eq!(db.config["MARIADB_DATABASE"], "shop");
+
+        assert!(import
+            .warnings
+            .contains(&"Service 'db': 'command' is not supported".to_string()));
+        for warning in [
+            "Service 'app': 'build' is not supported",
+            "Service 'app': 'ports' is not applied to the project's PHP container",
+            "Service 'app': 'volumes' is not applied to the project's PHP container",
+        ] {
+            assert!(import.warnings.contains(&warning.to_string()));
+        }
+        assert!(import
+            .warnings
+            .contains(&"Service 'worker' has no image and cannot be imported".to_string()));
+    }
+}
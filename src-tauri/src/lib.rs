@@ -1,11 +1,25 @@
+pub mod backend;
+pub mod certs;
+pub mod compose;
+pub mod compose_cli;
+pub mod dependencies;
+pub mod dns;
 pub mod docker;
+pub mod docker_api;
+pub mod import;
+pub mod runtime;
 pub mod system;
+pub mod trust;
 
 #[tauri::command]
 fn add_project(
     state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
     name: String,
     environment: HashMap<String, String>,
+    environment_targets: Option<HashMap<String, EnvTarget>>,
+    php_version: Option<String>,
+    aliases: Option<Vec<String>>,
 ) -> Result<(), String> {
     let mut app_state = state.0.lock().unwrap();
     let slug = system::normalize_slug(&name);
@@ -13,23 +27,29 @@ fn add_project(
         return Err(format!("Project with name '{}' already exists", name));
     }
 
+    let php_version = php_version.unwrap_or_else(default_php_version);
+    validate_php_version(&php_version)?;
+    let environment_targets = environment_targets.unwrap_or_default();
+    validate_environment_targets(&environment, &environment_targets)?;
+    let aliases = aliases.unwrap_or_default();
+    validate_aliases(&app_state, &slug, &aliases)?;
+
     // Créer le dossier du projet et fichier index.php par défaut
     system::create_project_dir(&name)?;
     // Créer la configuration Nginx pour ce projet
     system::create_nginx_config(&name)?;
 
-    let url = generate_project_url(&slug);
-    app_state.projects.insert(
-        slug.clone(),
-        Project {
-            name,
-            slug,
-            services: vec![],
-            url,
-            environment,
-        },
-    );
+    let mut project = Project::new(name, &app_state.settings.base_domain);
+    project.environment = environment;
+    project.environment_targets = environment_targets;
+    project.php_version = php_version;
+    project.aliases = aliases;
+    app_state.projects.insert(slug, project);
     save_config(&app_state)?;
+    let snapshot = app_state.clone();
+    drop(app_state);
+    sync_hosts_file(&required_hostnames(&snapshot));
+    refresh_certificates(&snapshot, runtime.0.as_ref());
     Ok(())
 }
 
@@ -55,6 +75,17 @@ fn remove_project(state: tauri::State<'_, AppStateWrapper>, name: String) -> Res
         })?;
     }
 
+    // Supprimer l'override compose du projet
+    let override_path = system::get_compose_override_path(Some(&slug))?;
+    if override_path.exists() {
+        fs::remove_file(&override_path).map_err(|e| {
+            format!(
+                "Failed to remove compose override for project '{}': {}",
+                name, e
+            )
+        })?;
+    }
+
     // Supprimer le dossier du projet si possible
     let project_dir = docker_dir.join("projects").join(slug.clone());
     if project_dir.exists() {
@@ -72,14 +103,21 @@ fn remove_project(state: tauri::State<'_, AppStateWrapper>, name: String) -> Res
 
     app_state.projects.remove(&slug);
     save_config(&app_state)?;
+    let hostnames = required_hostnames(&app_state);
+    drop(app_state);
+    sync_hosts_file(&hostnames);
     Ok(())
 }
 
 #[tauri::command]
 fn update_project(
     state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
     name: String,
     environment: HashMap<String, String>,
+    environment_targets: Option<HashMap<String, EnvTarget>>,
+    php_version: Option<String>,
+    aliases: Option<Vec<String>>,
 ) -> Result<(), String> {
     let mut app_state = state.0.lock().unwrap();
     let slug = system::normalize_slug(&name);
@@ -87,8 +125,54 @@ fn update_project(
         return Err(format!("Project '{}' does not exist", name));
     }
 
+    if let Some(version) = &php_version {
+        validate_php_version(version)?;
+    }
+    if let Some(aliases) = &aliases {
+        validate_aliases(&app_state, &slug, aliases)?;
+    }
+
     if let Some(project) = app_state.projects.get_mut(&slug) {
+        let mut environment_targets =
+            environment_targets.unwrap_or_else(|| project.environment_targets.clone());
+        // Les variables supprimées n'ont plus de cible
+        environment_targets.retain(|key, _| environment.contains_key(key));
+        validate_environment_targets(&environment, &environment_targets)?;
+
         project.environment = environment;
+        project.environment_targets = environment_targets;
+        if let Some(version) = php_version {
+            project.php_version = version;
+        }
+        let aliases_changed = aliases.is_some();
+        if let Some(aliases) = aliases {
+            project.aliases = aliases;
+        }
+        save_config(&app_state)?;
+        if aliases_changed {
+            let snapshot = app_state.clone();
+            drop(app_state);
+            sync_hosts_file(&required_hostnames(&snapshot));
+            refresh_certificates(&snapshot, runtime.0.as_ref());
+        }
+        Ok(())
+    } else {
+        Err(format!("Project '{}' does not exist", name))
+    }
+}
+
+#[tauri::command]
+fn update_project_resources(
+    state: tauri::State<'_, AppStateWrapper>,
+    name: String,
+    resources: ResourceSettings,
+) -> Result<(), String> {
+    let mut app_state = state.0.lock().unwrap();
+    let slug = system::normalize_slug(&name);
+    resources.validate()?;
+
+    if let Some(project) = app_state.projects.get_mut(&slug) {
+        project.resources = resources;
         save_config(&app_state)?;
         Ok(())
     } else {
@@ -106,22 +190,43 @@ fn add_service(state: tauri::State<'_, AppStateWrapper>, service: Service) -> Re
         ));
     }
 
+    service.resources.validate()?;
+
     // Validate service dependencies
-    for dependency in &service.dependencies {
-        if !app_state.services.contains_key(dependency) && dependency != &service.name {
-            return Err(format!("Dependency '{}' does not exist", dependency));
-        }
-    }
+    let mut services = app_state.services.clone();
+    services.insert(service.name.clone(), service.clone());
+    dependencies::validate(&services)?;
 
     app_state.services.insert(service.name.clone(), service);
     save_config(&app_state)?;
+    let hostnames = required_hostnames(&app_state);
+    drop(app_state);
+    sync_hosts_file(&hostnames);
     Ok(())
 }
 
 #[tauri::command]
-fn remove_service(state: tauri::State<'_, AppStateWrapper>, name: String) -> Result<(), String> {
+fn remove_service(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+    name: String,
+) -> Result<(), String> {
     let mut app_state = state.0.lock().unwrap();
-    if !app_state.services.contains_key(&name) {
+    remove_service_and_container(&mut app_state, runtime.0.as_ref(), &name)?;
+    save_config(&app_state)?;
+    let hostnames = required_hostnames(&app_state);
+    drop(app_state);
+    sync_hosts_file(&hostnames);
+    Ok(())
+}
+
+// Arrête et supprime le conteneur du service, puis le retire de la configuration
+fn remove_service_and_container(
+    app_state: &mut AppState,
+    runtime: &dyn ContainerRuntime,
+    name: &str,
+) -> Result<(), String> {
+    if !app_state.services.contains_key(name) {
         return Err(format!("Service '{}' does not exist", name));
     }
 
@@ -129,7 +234,7 @@ fn remove_service(state: tauri::State<'_, AppStateWrapper>, name: String) -> Res
     let dependency_for: Vec<String> = app_state
         .services
         .iter()
-        .filter(|(_, service)| service.dependencies.contains(&name))
+        .filter(|(_, service)| service.dependencies.iter().any(|d| d == name))
         .map(|(service_name, _)| service_name.clone())
         .collect();
 
@@ -142,28 +247,27 @@ fn remove_service(state: tauri::State<'_, AppStateWrapper>, name: String) -> Res
     }
 
     // Stop and remove the container if it exists
-    let down_command = format!("docker-compose stop {} && docker-compose rm -f {}", name, name);
-    let output = Command::new("sh")
-        .args(["-c", &down_command])
-        .current_dir(system::get_docker_compose_dir()?)
-        .output()
-        .map_err(|e| format!("Failed to remove container: {}", e))?;
-
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        eprintln!("Warning while removing container: {}", error);
-        // On continue même si la suppression du conteneur échoue
-        // car il est possible que le conteneur n'existe pas
+    for args in [vec!["stop", name], vec!["rm", "-f", name]] {
+        let args: Vec<String> = args.into_iter().map(String::from).collect();
+        let output = runtime
+            .compose(&app_state.settings, &args)
+            .map_err(|e| format!("Failed to remove container: {}", e))?;
+
+        if !output.success {
+            eprintln!("Warning while removing container: {}", output.stderr);
+            // On continue même si la suppression du conteneur échoue
+            // car il est possible que le conteneur n'existe pas
+            break;
+        }
     }
 
     // Remove the service from all projects that use it
     for project in app_state.projects.values_mut() {
-        project.services.retain(|s| s != &name);
+        project.services.retain(|s| s != name);
     }
 
     // Remove the service itself
-    app_state.services.remove(&name);
-    save_config(&app_state)?;
+    app_state.services.remove(name);
     Ok(())
 }
 
@@ -177,19 +281,30 @@ fn update_service(
         return Err(format!("Service '{}' does not exist", service.name));
     }
 
+    service.resources.validate()?;
+
     // Validate service dependencies
-    for dependency in &service.dependencies {
-        if !app_state.services.contains_key(dependency) && dependency != &service.name {
-            return Err(format!("Dependency '{}' does not exist", dependency));
-        }
-    }
+    let mut services = app_state.services.clone();
+    services.insert(service.name.clone(), service.clone());
+    dependencies::validate(&services)?;
 
     // Update the service
     app_state.services.insert(service.name.clone(), service);
     save_config(&app_state)?;
+    let hostnames = required_hostnames(&app_state);
+    drop(app_state);
+    sync_hosts_file(&hostnames);
     Ok(())
 }
 
+#[tauri::command]
+fn get_service_start_order(
+    state: tauri::State<'_, AppStateWrapper>,
+) -> Result<Vec<String>, String> {
+    let app_state = state.0.lock().unwrap();
+    dependencies::start_order(&app_state.services)
+}
+
 #[tauri::command]
 fn list_projects(state: tauri::State<'_, AppStateWrapper>) -> Vec<Project> {
     let app_state = state.0.lock().unwrap();
@@ -267,87 +382,736 @@ fn save_docker_compose(content: String) -> Result<(), String> {
     std::fs::create_dir_all(&docker_compose_dir)
         .map_err(|e| format!("Failed to create docker-compose directory: {}", e))?;
     let docker_compose_path = docker_compose_dir.join("docker-compose.yml");
-    fs::write(docker_compose_path, content)
+    fs::write(docker_compose_path, &content)
+        .map_err(|e| format!("Failed to save docker-compose.yml: {}", e))?;
+    let generated_copy = docker_compose_dir.join(system::GENERATED_COMPOSE_FILE);
+    fs::write(generated_copy, content)
         .map_err(|e| format!("Failed to save docker-compose.yml: {}", e))
 }
 
+// Ce que la régénération changerait dans le docker-compose.yml actuel,
+// y compris les modifications faites à la main qui seraient écrasées
+#[tauri::command]
+fn diff_docker_compose(
+    state: tauri::State<'_, AppStateWrapper>,
+) -> Result<compose::ComposeDiff, String> {
+    let app_state = state.0.lock().unwrap();
+    dependencies::validate(&app_state.services)?;
+    let generated = docker::build_compose_file(&app_state);
+
+    let docker_dir = system::get_docker_compose_dir()?;
+    let docker_compose_path = docker_dir.join(system::COMPOSE_FILE);
+    if !docker_compose_path.exists() {
+        return Ok(compose::ComposeFile::default().diff(&generated));
+    }
+
+    let content = fs::read_to_string(&docker_compose_path)
+        .map_err(|e| format!("Failed to read docker-compose.yml: {}", e))?;
+    let last_saved = fs::read_to_string(docker_dir.join(system::GENERATED_COMPOSE_FILE)).ok();
+    let mut diff = compose::ComposeFile::from_yaml(&content)?.diff(&generated);
+    diff.edited_by_hand = compose::ComposeFile::edited_by_hand(&content, last_saved.as_deref())?;
+    Ok(diff)
+}
+
+// Slug du projet ciblé par un override, ou None pour l'override global
+fn override_slug(state: &AppState, project_name: Option<&str>) -> Result<Option<String>, String> {
+    match project_name {
+        Some(name) => {
+            let slug = system::normalize_slug(name);
+            if !state.projects.contains_key(&slug) {
+                return Err(format!("Project '{}' does not exist", name));
+            }
+            Ok(Some(slug))
+        }
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+fn get_compose_override(
+    state: tauri::State<'_, AppStateWrapper>,
+    project_name: Option<String>,
+) -> Result<String, String> {
+    let app_state = state.0.lock().unwrap();
+    let slug = override_slug(&app_state, project_name.as_deref())?;
+    let override_path = system::get_compose_override_path(slug.as_deref())?;
+    if !override_path.exists() {
+        return Ok(String::new());
+    }
+    fs::read_to_string(&override_path)
+        .map_err(|e| format!("Failed to read compose override: {}", e))
+}
+
+#[tauri::command]
+fn save_compose_override(
+    state: tauri::State<'_, AppStateWrapper>,
+    project_name: Option<String>,
+    content: String,
+) -> Result<(), String> {
+    let app_state = state.0.lock().unwrap();
+    let slug = override_slug(&app_state, project_name.as_deref())?;
+    let override_path = system::get_compose_override_path(slug.as_deref())?;
+
+    // Un override vide supprime le fichier
+    if content.trim().is_empty() {
+        if override_path.exists() {
+            fs::remove_file(&override_path)
+                .map_err(|e| format!("Failed to remove compose override: {}", e))?;
+        }
+        return Ok(());
+    }
+
+    compose::ComposeFile::from_yaml(&content)?;
+    if let Some(parent) = override_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create overrides directory: {}", e))?;
+    }
+    fs::write(&override_path, content)
+        .map_err(|e| format!("Failed to save compose override: {}", e))
+}
+
+#[tauri::command]
+fn validate_compose_overrides(
+    state: tauri::State<'_, AppStateWrapper>,
+) -> Result<compose::ComposeValidation, String> {
+    let app_state = state.0.lock().unwrap();
+    let mut merged = docker::build_compose_file(&app_state);
+
+    // Les overrides sont fusionnés dans le même ordre que pour docker-compose
+    for file in system::get_compose_files()?.iter().skip(1) {
+        let content = fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read '{}': {}", file.display(), e))?;
+        merged.merge(compose::ComposeFile::from_yaml(&content)?);
+    }
+
+    Ok(compose::ComposeValidation {
+        errors: merged.validate(),
+        merged: merged.to_yaml()?,
+    })
+}
+
+#[tauri::command]
+fn preview_compose_import(
+    state: tauri::State<'_, AppStateWrapper>,
+    path: String,
+    project_name: Option<String>,
+) -> Result<import::ComposeImport, String> {
+    let path = std::path::PathBuf::from(path);
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let compose = compose::ComposeFile::from_yaml(&content)?;
+
+    let base_dir = path
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_default();
+    // Par défaut, le projet prend le nom du dossier qui contient le fichier
+    let project_name = project_name
+        .or_else(|| {
+            base_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .ok_or_else(|| "Could not determine a project name".to_string())?;
+
+    let app_state = state.0.lock().unwrap();
+    Ok(import::import_compose(
+        &compose,
+        &project_name,
+        &base_dir,
+        &app_state,
+    ))
+}
+
+// L'aperçu vient du client : le slug et l'URL sont recalculés depuis le nom,
+// le reste est revalidé comme pour add_project
+fn imported_project(state: &AppState, imported: Project) -> Result<Project, String> {
+    let mut project = Project::new(imported.name, &state.settings.base_domain);
+    if state.projects.contains_key(&project.slug) {
+        return Err(format!(
+            "Project with name '{}' already exists",
+            project.name
+        ));
+    }
+    validate_php_version(&imported.php_version)?;
+    validate_environment_targets(&imported.environment, &imported.environment_targets)?;
+    validate_aliases(state, &project.slug, &imported.aliases)?;
+    imported.resources.validate()?;
+
+    project.services = imported.services;
+    project.environment = imported.environment;
+    project.environment_targets = imported.environment_targets;
+    project.php_version = imported.php_version;
+    project.resources = imported.resources;
+    project.aliases = imported.aliases;
+    Ok(project)
+}
+
+#[tauri::command]
+fn apply_compose_import(
+    state: tauri::State<'_, AppStateWrapper>,
+    import: import::ComposeImport,
+) -> Result<(), String> {
+    let mut app_state = state.0.lock().unwrap();
+    let project = imported_project(&app_state, import.project)?;
+
+    let mut services = app_state.services.clone();
+    for service in &import.services {
+        if app_state.services.contains_key(&service.name) {
+            return Err(format!(
+                "Service with name '{}' already exists",
+                service.name
+            ));
+        }
+        service.resources.validate()?;
+        services.insert(service.name.clone(), service.clone());
+    }
+    dependencies::validate(&services)?;
+    if let Some(unknown) = project
+        .services
+        .iter()
+        .find(|name| !services.contains_key(*name))
+    {
+        return Err(format!("Service '{}' does not exist", unknown));
+    }
+
+    system::create_project_dir(&project.name)?;
+    system::create_nginx_config(&project.name)?;
+
+    for service in import.services {
+        app_state.services.insert(service.name.clone(), service);
+    }
+    app_state.projects.insert(project.slug.clone(), project);
+    save_config(&app_state)?;
+    let hostnames = required_hostnames(&app_state);
+    drop(app_state);
+    sync_hosts_file(&hostnames);
+    Ok(())
+}
+
 #[tauri::command]
-fn start_environment() -> Result<String, String> {
+fn start_environment(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<String, String> {
+    let app_state = state.0.lock().unwrap().clone();
+    start_environment_with(&app_state, runtime.0.as_ref())
+}
+
+fn start_environment_with(
+    app_state: &AppState,
+    runtime: &dyn ContainerRuntime,
+) -> Result<String, String> {
+    // Check if the container engine is running
+    let backend = app_state.settings.container_backend;
+    if !runtime.is_running(backend)? {
+        return Err(format!(
+            "{} is not running. {}",
+            backend.label(),
+            backend.start_hint()
+        ));
+    }
+
     let docker_compose_path = system::get_docker_compose_dir()?.join("docker-compose.yml");
-    
     if !docker_compose_path.exists() {
         return Err("Docker Compose file not found. Generate configuration first.".to_string());
     }
-    
-    // Check if Docker is running
-    if !system::is_docker_running()? {
-        return Err("Docker is not running. Please start it first.".to_string());
+
+    renew_certificates_if_needed(app_state, runtime)?;
+
+    // Les ports déjà tenus par les conteneurs de l'environnement ne sont pas
+    // des conflits ; ceux qui viennent d'être ajoutés sont vérifiés
+    let owned = runtime.environment_ports(backend)?;
+    let conflicts = system::check_port_conflicts(&read_compose_files()?, &owned);
+    if !conflicts.is_empty() {
+        let ports: Vec<String> = conflicts
+            .iter()
+            .map(|conflict| match &conflict.process {
+                Some(process) => format!(
+                    "{}/{} ({}, used by {})",
+                    conflict.port, conflict.protocol, conflict.service, process
+                ),
+                None => format!(
+                    "{}/{} ({})",
+                    conflict.port, conflict.protocol, conflict.service
+                ),
+            })
+            .collect();
+        return Err(format!("Ports already in use: {}", ports.join(", ")));
     }
-    
-    let output = Command::new("docker-compose")
-        .current_dir(system::get_docker_compose_dir()?)
-        .args(["up", "-d"])
-        .output()
+
+    let output = runtime
+        .compose(&app_state.settings, &["up".to_string(), "-d".to_string()])
         .map_err(|e| format!("Failed to start environment: {}", e))?;
-        
-    if output.status.success() {
+
+    if output.success {
         Ok("Environment started successfully".to_string())
     } else {
-        let error = String::from_utf8_lossy(&output.stderr).to_string();
-        Err(format!("Failed to start environment: {}", error))
+        Err(format!("Failed to start environment: {}", output.stderr))
+    }
+}
+
+// Compose tel que docker-compose le verra : fichier généré et overrides fusionnés
+fn read_compose_files() -> Result<compose::ComposeFile, String> {
+    let mut merged = compose::ComposeFile::default();
+    for file in system::get_compose_files()? {
+        let content = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read '{}': {}", file.display(), e))?;
+        merged.merge(compose::ComposeFile::from_yaml(&content)?);
     }
+    Ok(merged)
 }
 
 #[tauri::command]
-fn stop_environment() -> Result<String, String> {
+fn check_port_conflicts(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<Vec<system::PortConflict>, String> {
+    let backend = state.0.lock().unwrap().settings.container_backend;
+    // Moteur arrêté : aucun port n'est tenu par l'environnement
+    let owned = runtime.0.environment_ports(backend).unwrap_or_default();
+    let docker_compose_path = system::get_docker_compose_dir()?.join(system::COMPOSE_FILE);
+    // Sans fichier sur disque, on vérifie la configuration qui serait générée
+    let compose = if docker_compose_path.exists() {
+        read_compose_files()?
+    } else {
+        docker::build_compose_file(&state.0.lock().unwrap())
+    };
+    Ok(system::check_port_conflicts(&compose, &owned))
+}
+
+#[tauri::command]
+fn stop_environment(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<String, String> {
     let docker_compose_path = system::get_docker_compose_dir()?.join("docker-compose.yml");
     
     if !docker_compose_path.exists() {
         return Err("Docker Compose file not found. Generate configuration first.".to_string());
     }
     
-    let output = Command::new("docker-compose")
-        .current_dir(system::get_docker_compose_dir()?)
-        .args(["down"])
-        .output()
+    let settings = state.0.lock().unwrap().settings.clone();
+    let output = runtime
+        .0
+        .compose(&settings, &["down".to_string()])
         .map_err(|e| format!("Failed to stop environment: {}", e))?;
         
-    if output.status.success() {
+    if output.success {
         Ok("Environment stopped successfully".to_string())
     } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to stop environment: {}", error))
+        Err(format!("Failed to stop environment: {}", output.stderr))
+    }
+}
+
+// Conteneurs démarrés ou arrêtés par une action sur un seul projet
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ProjectContainers {
+    project: String,
+    started: Vec<String>,
+    stopped: Vec<String>,
+}
+
+// Arrêt de l'environnement à la fermeture de l'application (signal, panique)
+pub fn shutdown_environment() -> Result<(), String> {
+    let settings = load_config()
+        .map(|state| state.settings)
+        .unwrap_or_default();
+    run_docker_compose(
+        &runtime::SystemRuntime::default(),
+        &settings,
+        &["down".to_string()],
+        "stop environment",
+    )
+}
+
+fn run_docker_compose(
+    runtime: &dyn ContainerRuntime,
+    settings: &Settings,
+    args: &[String],
+    action: &str,
+) -> Result<(), String> {
+    let output = runtime
+        .compose(settings, args)
+        .map_err(|e| format!("Failed to {}: {}", action, e))?;
+
+    if output.success {
+        Ok(())
+    } else {
+        Err(format!("Failed to {}: {}", action, output.stderr))
+    }
+}
+
+// Projet ciblé et compose sur disque, pour n'agir que sur ses conteneurs
+fn project_compose(
+    state: &tauri::State<'_, AppStateWrapper>,
+    runtime: &dyn ContainerRuntime,
+    name: &str,
+) -> Result<(Project, AppState, compose::ComposeFile), String> {
+    let docker_compose_path = system::get_docker_compose_dir()?.join(system::COMPOSE_FILE);
+    if !docker_compose_path.exists() {
+        return Err("Docker Compose file not found. Generate configuration first.".to_string());
+    }
+    let backend = state.0.lock().unwrap().settings.container_backend;
+    if !runtime.is_running(backend)? {
+        return Err(format!(
+            "{} is not running. {}",
+            backend.label(),
+            backend.start_hint()
+        ));
+    }
+
+    let state = state.0.lock().unwrap().clone();
+    let project = state
+        .projects
+        .get(&system::normalize_slug(name))
+        .cloned()
+        .ok_or_else(|| format!("Project '{}' does not exist", name))?;
+    Ok((project, state, read_compose_files()?))
+}
+
+fn start_project_containers(
+    runtime: &dyn ContainerRuntime,
+    project: &Project,
+    state: &AppState,
+    compose: &compose::ComposeFile,
+) -> Result<Vec<String>, String> {
+    let containers = docker::project_containers(compose, project);
+    let mut args = vec!["up".to_string(), "-d".to_string()];
+    args.extend(containers.iter().cloned());
+    run_docker_compose(
+        runtime,
+        &state.settings,
+        &args,
+        &format!("start project '{}'", project.name),
+    )?;
+    Ok(containers)
+}
+
+fn stop_project_containers(
+    runtime: &dyn ContainerRuntime,
+    project: &Project,
+    state: &AppState,
+    compose: &compose::ComposeFile,
+) -> Result<Vec<String>, String> {
+    let containers = docker::project_exclusive_containers(compose, state, project);
+    if !containers.is_empty() {
+        let mut args = vec!["stop".to_string()];
+        args.extend(containers.iter().cloned());
+        run_docker_compose(
+            runtime,
+            &state.settings,
+            &args,
+            &format!("stop project '{}'", project.name),
+        )?;
     }
+    Ok(containers)
 }
 
 #[tauri::command]
-fn check_docker_status() -> Result<bool, String> {
-    system::is_docker_running()
+fn start_project(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+    name: String,
+) -> Result<ProjectContainers, String> {
+    let runtime = runtime.0.as_ref();
+    let (project, state, compose) = project_compose(&state, runtime, &name)?;
+    renew_certificates_if_needed(&state, runtime)?;
+    Ok(ProjectContainers {
+        started: start_project_containers(runtime, &project, &state, &compose)?,
+        project: name,
+        ..Default::default()
+    })
 }
 
 #[tauri::command]
-fn check_environment_status() -> Result<bool, String> {
-    system::is_environment_running()
+fn stop_project(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+    name: String,
+) -> Result<ProjectContainers, String> {
+    let runtime = runtime.0.as_ref();
+    let (project, state, compose) = project_compose(&state, runtime, &name)?;
+    Ok(ProjectContainers {
+        stopped: stop_project_containers(runtime, &project, &state, &compose)?,
+        project: name,
+        ..Default::default()
+    })
 }
 
 #[tauri::command]
-fn setup_hosts_file() -> Result<(), String> {
-    system::setup_hosts(HTTPS_BASE_DOMAIN)
+fn restart_project(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+    name: String,
+) -> Result<ProjectContainers, String> {
+    let runtime = runtime.0.as_ref();
+    let (project, state, compose) = project_compose(&state, runtime, &name)?;
+    let stopped = stop_project_containers(runtime, &project, &state, &compose)?;
+    renew_certificates_if_needed(&state, runtime)?;
+    Ok(ProjectContainers {
+        started: start_project_containers(runtime, &project, &state, &compose)?,
+        stopped,
+        project: name,
+    })
 }
 
 #[tauri::command]
-fn check_hosts_entries() -> Result<Vec<(String, bool)>, String> {
-    system::check_hosts_entries(HTTPS_BASE_DOMAIN)
+fn check_docker_status(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<bool, String> {
+    runtime
+        .0
+        .is_running(state.0.lock().unwrap().settings.container_backend)
 }
 
 #[tauri::command]
-fn generate_traefik_config() -> Result<(), String> {
-    docker::generate_traefik_config()
+fn check_environment_status(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<bool, String> {
+    runtime
+        .0
+        .is_environment_running(state.0.lock().unwrap().settings.container_backend)
 }
 
 #[tauri::command]
-fn is_docker_installed() -> Result<bool, String> {
-    system::is_docker_installed()
+fn setup_hosts_file(
+    state: tauri::State<'_, AppStateWrapper>,
+    dry_run: Option<bool>,
+) -> Result<system::HostsUpdate, String> {
+    let hostnames = required_hostnames(&state.0.lock().unwrap());
+    system::setup_hosts(&hostnames, dry_run.unwrap_or(false))
+}
+
+// La configuration est enregistrée même si le fichier hosts n'a pas pu suivre
+// (élévation refusée...) : setup_hosts_file permet de réessayer. Appelée verrou
+// relâché, la demande d'élévation pouvant attendre l'utilisateur.
+fn sync_hosts_file(hostnames: &[String]) {
+    if let Err(e) = system::sync_hosts(hostnames) {
+        eprintln!("Failed to update hosts file: {}", e);
+    }
+}
+
+#[tauri::command]
+fn check_hosts_entries(
+    state: tauri::State<'_, AppStateWrapper>,
+) -> Result<Vec<(String, bool)>, String> {
+    let app_state = state.0.lock().unwrap();
+    system::check_hosts_entries(&required_hostnames(&app_state))
+}
+
+fn certificate_inventory(state: &AppState) -> Result<certs::CertificateInventory, String> {
+    certs::inspect_certificates(&docker::traefik_certs_dir()?, &required_hostnames(state))
+}
+
+// Réémet les certificats proches de l'expiration ou manquants, puis redémarre
+// Traefik s'il tourne pour qu'il les recharge
+fn renew_certificates_if_needed(
+    state: &AppState,
+    runtime: &dyn ContainerRuntime,
+) -> Result<bool, String> {
+    if !certificate_inventory(state)?.needs_renewal {
+        return Ok(false);
+    }
+    docker::generate_traefik_config(state)?;
+    if runtime.is_environment_running(state.settings.container_backend)? {
+        run_docker_compose(
+            runtime,
+            &state.settings,
+            &["restart".to_string(), "traefik".to_string()],
+            "restart Traefik",
+        )?;
+    }
+    Ok(true)
+}
+
+// Après un changement de noms servis : l'erreur est signalée sans annuler
+// l'enregistrement, le prochain démarrage retentera le renouvellement
+fn refresh_certificates(state: &AppState, runtime: &dyn ContainerRuntime) {
+    if !check_config_exists().unwrap_or(false) {
+        return;
+    }
+    if let Err(e) = renew_certificates_if_needed(state, runtime) {
+        eprintln!("Failed to renew certificates: {}", e);
+    }
+}
+
+#[tauri::command]
+fn get_certificate_inventory(
+    state: tauri::State<'_, AppStateWrapper>,
+) -> Result<certs::CertificateInventory, String> {
+    certificate_inventory(&state.0.lock().unwrap())
+}
+
+#[tauri::command]
+fn renew_certificates(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<certs::CertificateInventory, String> {
+    // Copie de l'état : le redémarrage de Traefik ne bloque pas les autres commandes
+    let app_state = state.0.lock().unwrap().clone();
+    renew_certificates_if_needed(&app_state, runtime.0.as_ref())?;
+    certificate_inventory(&app_state)
+}
+
+#[tauri::command]
+fn install_local_ca() -> Result<trust::TrustStatus, String> {
+    trust::install()
+}
+
+#[tauri::command]
+fn uninstall_local_ca() -> Result<trust::TrustStatus, String> {
+    trust::uninstall()
+}
+
+#[tauri::command]
+fn get_local_ca_status() -> Result<trust::TrustStatus, String> {
+    trust::status()
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<'_, AppStateWrapper>) -> Settings {
+    state.0.lock().unwrap().settings.clone()
+}
+
+#[tauri::command]
+fn update_settings(
+    state: tauri::State<'_, AppStateWrapper>,
+    dns_state: tauri::State<'_, DnsServerWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+    settings: Settings,
+) -> Result<(), String> {
+    let base_domain = settings.base_domain.trim().to_lowercase();
+    validate_hostname(&base_domain)?;
+    settings
+        .dns_address
+        .parse::<std::net::SocketAddr>()
+        .map_err(|_| format!("Invalid DNS server address '{}'", settings.dns_address))?;
+
+    let mut app_state = state.0.lock().unwrap();
+    let domain_changed = app_state.settings.base_domain != base_domain;
+    if domain_changed {
+        validate_base_domain_change(&app_state, &base_domain)?;
+    }
+    let previous = std::mem::replace(&mut app_state.settings.base_domain, base_domain.clone());
+    if domain_changed {
+        change_base_domain(&mut app_state, &previous, &base_domain);
+    }
+    app_state.settings.dns_enabled = settings.dns_enabled;
+    app_state.settings.dns_address = settings.dns_address;
+    let backend_changed = app_state.settings.container_backend != settings.container_backend;
+    if backend_changed || app_state.settings.compose_providers != settings.compose_providers {
+        runtime.0.reset_compose();
+    }
+    app_state.settings.compose_providers = settings.compose_providers;
+    app_state.settings.container_backend = settings.container_backend;
+    if backend_changed {
+        app_state.settings.engine_socket = settings.container_backend.socket_path();
+    }
+    save_config(&app_state)?;
+    // Traefik monte la socket du moteur : le docker-compose.yml doit suivre
+    let regenerated = if backend_changed {
+        regenerate_docker_compose(&app_state)
+    } else {
+        Ok(())
+    };
+    let snapshot = app_state.clone();
+    drop(app_state);
+    sync_hosts_file(&required_hostnames(&snapshot));
+    // Les nouveaux noms des projets doivent être couverts avant le prochain démarrage
+    if domain_changed {
+        refresh_certificates(&snapshot, runtime.0.as_ref());
+    }
+    update_dns_server(&snapshot.settings, &mut dns_state.0.lock().unwrap())?;
+    regenerated
+}
+
+// Régénère un docker-compose.yml existant, sauf s'il a été modifié à la main :
+// l'utilisateur doit alors relire la régénération lui-même
+fn regenerate_docker_compose(state: &AppState) -> Result<(), String> {
+    let docker_dir = system::get_docker_compose_dir()?;
+    let docker_compose_path = docker_dir.join(system::COMPOSE_FILE);
+    if !docker_compose_path.exists() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(&docker_compose_path)
+        .map_err(|e| format!("Failed to read docker-compose.yml: {}", e))?;
+    let last_saved = fs::read_to_string(docker_dir.join(system::GENERATED_COMPOSE_FILE)).ok();
+    if compose::ComposeFile::edited_by_hand(&content, last_saved.as_deref())? {
+        return Err(format!(
+            "Settings saved, but docker-compose.yml was edited by hand: regenerate it to switch to {}",
+            state.settings.container_backend.label()
+        ));
+    }
+    save_docker_compose(docker::generate_docker_compose(state)?)
+}
+
+// Démarre, arrête ou redémarre le serveur DNS selon les réglages
+fn update_dns_server(
+    settings: &Settings,
+    server: &mut Option<dns::DnsServer>,
+) -> Result<(), String> {
+    let up_to_date = server.as_ref().map(|running| {
+        running.base_domain() == settings.base_domain
+            && running.address().to_string() == settings.dns_address
+    });
+    match (settings.dns_enabled, up_to_date) {
+        (true, Some(true)) | (false, None) => Ok(()),
+        (false, Some(_)) => {
+            *server = None;
+            Ok(())
+        }
+        (true, _) => {
+            // Libérer le port avant de relancer sur la même adresse
+            *server = None;
+            *server = Some(dns::DnsServer::start(
+                &settings.dns_address,
+                &settings.base_domain,
+            )?);
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DnsStatus {
+    enabled: bool,
+    running: bool,
+    address: String,
+    base_domain: String,
+    forwarding: system::DnsForwarding,
+}
+
+#[tauri::command]
+fn get_dns_status(
+    state: tauri::State<'_, AppStateWrapper>,
+    dns_state: tauri::State<'_, DnsServerWrapper>,
+) -> DnsStatus {
+    let settings = state.0.lock().unwrap().settings.clone();
+    DnsStatus {
+        enabled: settings.dns_enabled,
+        running: dns_state.0.lock().unwrap().is_some(),
+        forwarding: system::dns_forwarding_status(&settings.base_domain, &settings.dns_address),
+        address: settings.dns_address,
+        base_domain: settings.base_domain,
+    }
+}
+
+#[tauri::command]
+fn generate_traefik_config(state: tauri::State<'_, AppStateWrapper>) -> Result<(), String> {
+    docker::generate_traefik_config(&state.0.lock().unwrap())
+}
+
+#[tauri::command]
+fn is_docker_installed(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<bool, String> {
+    runtime
+        .0
+        .is_installed(state.0.lock().unwrap().settings.container_backend)
 }
 
 #[tauri::command]
@@ -372,17 +1136,27 @@ fn reset_config() -> Result<(), String> {
 }
 
 #[tauri::command]
-fn get_system_info() -> Result<system::SystemInfo, String> {
-    system::get_system_info()
+fn get_system_info(
+    state: tauri::State<'_, AppStateWrapper>,
+    runtime: tauri::State<'_, RuntimeWrapper>,
+) -> Result<system::SystemInfo, String> {
+    let settings = state.0.lock().unwrap().settings.clone();
+    let backend = settings.container_backend;
+    system::get_system_info(
+        backend,
+        runtime.0.version(backend),
+        runtime.0.compose_info(&settings).ok(),
+    )
 }
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::process::Command;
 use std::sync::Mutex;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+use runtime::ContainerRuntime;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Service {
     name: String,
     image: String,
@@ -391,6 +1165,119 @@ pub struct Service {
     global: bool,
     dependencies: Vec<String>,
     config: HashMap<String, String>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    #[serde(default)]
+    healthcheck: Option<ServiceHealthcheck>,
+    #[serde(default)]
+    resources: ResourceSettings,
+}
+
+// Limites de ressources et politique de redémarrage d'un conteneur
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ResourceSettings {
+    // Format docker : "512m", "2g"...
+    #[serde(default)]
+    memory_limit: Option<String>,
+    // Nombre de CPU, fractions acceptées : "0.5", "2"...
+    #[serde(default)]
+    cpu_limit: Option<String>,
+    #[serde(default)]
+    restart: Option<RestartPolicy>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    No,
+    Always,
+    OnFailure,
+    UnlessStopped,
+}
+
+impl RestartPolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RestartPolicy::No => "no",
+            RestartPolicy::Always => "always",
+            RestartPolicy::OnFailure => "on-failure",
+            RestartPolicy::UnlessStopped => "unless-stopped",
+        }
+    }
+
+    fn parse(value: &str) -> Option<RestartPolicy> {
+        // "on-failure:3" garde seulement la politique
+        match value.split(':').next().unwrap_or(value) {
+            "no" => Some(RestartPolicy::No),
+            "always" => Some(RestartPolicy::Always),
+            "on-failure" => Some(RestartPolicy::OnFailure),
+            "unless-stopped" => Some(RestartPolicy::UnlessStopped),
+            _ => None,
+        }
+    }
+}
+
+impl ResourceSettings {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(memory) = &self.memory_limit {
+            let lowercase = memory.to_lowercase();
+            let digits = ["kb", "mb", "gb", "b", "k", "m", "g"]
+                .iter()
+                .find_map(|unit| lowercase.strip_suffix(unit))
+                .unwrap_or(&lowercase);
+            let valid = digits.parse::<f64>().is_ok_and(|value| value > 0.0);
+            if !valid {
+                return Err(format!(
+                    "Invalid memory limit '{}'. Use a value like 512m or 2g",
+                    memory
+                ));
+            }
+        }
+        if let Some(cpus) = &self.cpu_limit {
+            if !cpus.parse::<f64>().is_ok_and(|value| value > 0.0) {
+                return Err(format!(
+                    "Invalid CPU limit '{}'. Use a number of CPUs like 0.5 or 2",
+                    cpus
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Commande de disponibilité d'un service, exécutée dans le conteneur via CMD-SHELL
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServiceHealthcheck {
+    command: String,
+    #[serde(default = "default_healthcheck_interval")]
+    interval: String,
+    #[serde(default = "default_healthcheck_retries")]
+    retries: u32,
+    #[serde(default = "default_healthcheck_start_period")]
+    start_period: String,
+}
+
+impl ServiceHealthcheck {
+    fn new(command: &str) -> ServiceHealthcheck {
+        ServiceHealthcheck {
+            command: command.to_string(),
+            interval: default_healthcheck_interval(),
+            retries: default_healthcheck_retries(),
+            start_period: default_healthcheck_start_period(),
+        }
+    }
+}
+
+fn default_healthcheck_interval() -> String {
+    "10s".to_string()
+}
+
+fn default_healthcheck_retries() -> u32 {
+    5
+}
+
+fn default_healthcheck_start_period() -> String {
+    "30s".to_string()
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -400,6 +1287,68 @@ pub struct Project {
     services: Vec<String>,
     url: String,
     environment: HashMap<String, String>,
+    #[serde(default)]
+    environment_targets: HashMap<String, EnvTarget>,
+    #[serde(default = "default_php_version")]
+    php_version: String,
+    // Valeurs appliquées aux conteneurs php et nginx du projet
+    #[serde(default)]
+    resources: ResourceSettings,
+    // Noms d'hôte supplémentaires servis par le nginx du projet ("client.test"...)
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+// Conteneur du projet qui reçoit une variable d'environnement
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvTarget {
+    #[default]
+    Php,
+    Nginx,
+    Both,
+}
+
+impl Project {
+    fn new(name: String, base_domain: &str) -> Project {
+        let slug = system::normalize_slug(&name);
+        let url = generate_project_url(&slug, base_domain);
+        Project {
+            name,
+            slug,
+            services: vec![],
+            url,
+            environment: HashMap::new(),
+            environment_targets: HashMap::new(),
+            php_version: default_php_version(),
+            resources: ResourceSettings::default(),
+            aliases: Vec::new(),
+        }
+    }
+
+    // Nom d'hôte principal suivi des alias
+    fn hostnames(&self) -> Vec<String> {
+        let mut hostnames = vec![self.url.trim_start_matches("https://").to_string()];
+        hostnames.extend(self.aliases.iter().cloned());
+        hostnames
+    }
+
+    // Variables destinées à un conteneur donné, triées par nom.
+    // Sans cible explicite, une variable va au conteneur php.
+    fn environment_for(&self, container: EnvTarget) -> BTreeMap<&str, &str> {
+        self.environment
+            .iter()
+            .filter(|(key, _)| {
+                let target = self
+                    .environment_targets
+                    .get(key.as_str())
+                    .copied()
+                    .unwrap_or_default();
+                target == container || target == EnvTarget::Both
+            })
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -411,10 +1360,11 @@ pub struct PredefinedService {
     requires_traefik: bool,
     environment: HashMap<String, String>,
     volumes: Vec<String>,
+    healthcheck: Option<ServiceHealthcheck>,
 }
 
 impl PredefinedService {
-    fn to_service(&self) -> Service {
+    fn to_service(&self, base_domain: &str) -> Service {
         let mut service = Service {
             name: self.name.clone(),
             image: self.image.clone(),
@@ -423,26 +1373,34 @@ impl PredefinedService {
             global: true,
             dependencies: Vec::new(),
             config: self.environment.clone(),
+            labels: HashMap::new(),
+            healthcheck: self.healthcheck.clone(),
+            resources: ResourceSettings::default(),
         };
 
         if let Some(port) = self.port {
             if self.requires_traefik {
                 // Ne pas exposer le port directement, utiliser Traefik
-                let domain = format!("{}.local.test", self.name.to_lowercase());
-                let mut labels = HashMap::new();
+                let router = self.name.to_lowercase();
+                let domain = format!("{}.{}", router, base_domain);
+                let labels = &mut service.labels;
                 labels.insert("traefik.enable".to_string(), "true".to_string());
                 labels.insert(
-                    format!("traefik.http.routers.{}.rule", self.name.to_lowercase()),
+                    format!("traefik.http.routers.{}.rule", router),
                     format!("Host(`{}`)", domain),
                 );
                 labels.insert(
-                    format!(
-                        "traefik.http.services.{}.loadbalancer.server.port",
-                        self.name.to_lowercase()
-                    ),
+                    format!("traefik.http.routers.{}.entrypoints", router),
+                    "websecure".to_string(),
+                );
+                labels.insert(
+                    format!("traefik.http.routers.{}.tls", router),
+                    "true".to_string(),
+                );
+                labels.insert(
+                    format!("traefik.http.services.{}.loadbalancer.server.port", router),
                     port.to_string(),
                 );
-                service.config.extend(labels);
             } else {
                 // Service interne, pas d'exposition de port
             }
@@ -474,6 +1432,9 @@ fn get_predefined_services() -> Vec<PredefinedService> {
                 env
             },
             volumes: vec!["mysql8_data:/var/lib/mysql".to_string()],
+            healthcheck: Some(ServiceHealthcheck::new(
+                "mysqladmin ping -h localhost -u root -p$$MYSQL_ROOT_PASSWORD",
+            )),
         },
         PredefinedService {
             name: "Redis".to_string(),
@@ -483,6 +1444,7 @@ fn get_predefined_services() -> Vec<PredefinedService> {
             requires_traefik: false,
             environment: HashMap::new(),
             volumes: vec![],
+            healthcheck: Some(ServiceHealthcheck::new("redis-cli ping")),
         },
         PredefinedService {
             name: "PhpMyAdmin".to_string(),
@@ -500,6 +1462,9 @@ fn get_predefined_services() -> Vec<PredefinedService> {
                 env
             },
             volumes: vec![],
+            healthcheck: Some(ServiceHealthcheck::new(
+                "curl -fs http://localhost/ || exit 1",
+            )),
         },
         PredefinedService {
             name: "MailHog".to_string(),
@@ -509,10 +1474,18 @@ fn get_predefined_services() -> Vec<PredefinedService> {
             requires_traefik: true,
             environment: HashMap::new(),
             volumes: vec![],
+            healthcheck: Some(ServiceHealthcheck::new(
+                "wget -q --spider http://localhost:8025 || exit 1",
+            )),
         },
     ]
 }
 
+#[tauri::command]
+fn list_php_versions() -> Vec<String> {
+    SUPPORTED_PHP_VERSIONS.iter().map(|v| v.to_string()).collect()
+}
+
 #[tauri::command]
 fn list_predefined_services() -> Vec<PredefinedService> {
     get_predefined_services()
@@ -528,8 +1501,8 @@ fn add_predefined_service(
         .find(|s| s.name == name)
         .ok_or_else(|| format!("Predefined service '{}' not found", name))?;
 
-    let service = predefined.to_service();
     let mut app_state = state.0.lock().unwrap();
+    let service = predefined.to_service(&app_state.settings.base_domain);
 
     // Vérifier si le service existe déjà
     if app_state.services.contains_key(&service.name) {
@@ -538,6 +1511,9 @@ fn add_predefined_service(
 
     app_state.services.insert(service.name.clone(), service);
     save_config(&app_state)?;
+    let hostnames = required_hostnames(&app_state);
+    drop(app_state);
+    sync_hosts_file(&hostnames);
     Ok(())
 }
 
@@ -545,10 +1521,182 @@ fn add_predefined_service(
 pub struct AppState {
     services: HashMap<String, Service>,
     projects: HashMap<String, Project>,
+    #[serde(default)]
+    settings: Settings,
+}
+
+// Réglages globaux de l'application
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Settings {
+    // Domaine sous lequel les projets sont servis : <slug>.<base_domain>
+    #[serde(default = "default_base_domain")]
+    base_domain: String,
+    // Serveur DNS intégré, alternative au fichier hosts
+    #[serde(default)]
+    dns_enabled: bool,
+    #[serde(default = "default_dns_address")]
+    dns_address: String,
+    // Outils compose essayés dans cet ordre ; vide = ordre du moteur
+    #[serde(default)]
+    compose_providers: Vec<compose_cli::ComposeProvider>,
+    #[serde(default)]
+    container_backend: backend::ContainerBackend,
+    // Socket du moteur montée dans Traefik : résolue au démarrage et au changement
+    // de moteur, pour que la génération du compose ne lise pas l'environnement
+    #[serde(skip, default = "default_engine_socket")]
+    engine_socket: std::path::PathBuf,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            base_domain: default_base_domain(),
+            dns_enabled: false,
+            dns_address: default_dns_address(),
+            compose_providers: Vec::new(),
+            container_backend: backend::ContainerBackend::default(),
+            engine_socket: default_engine_socket(),
+        }
+    }
 }
 
 const CONFIG_FILE: &str = "config.json";
-const HTTPS_BASE_DOMAIN: &str = "local.test";
+const DEFAULT_BASE_DOMAIN: &str = "local.test";
+// 5353 est pris par mDNS (avahi), 53 demande les droits root
+const DEFAULT_DNS_ADDRESS: &str = "127.0.0.1:5300";
+const DEFAULT_ENGINE_SOCKET: &str = "/var/run/docker.sock";
+const DEFAULT_PHP_VERSION: &str = "8.2";
+// Versions disponibles en tag `<version>-fpm` sur l'image officielle php
+pub(crate) const SUPPORTED_PHP_VERSIONS: &[&str] = &["7.4", "8.0", "8.1", "8.2", "8.3"];
+
+fn default_php_version() -> String {
+    DEFAULT_PHP_VERSION.to_string()
+}
+
+fn validate_environment_targets(
+    environment: &HashMap<String, String>,
+    targets: &HashMap<String, EnvTarget>,
+) -> Result<(), String> {
+    for key in targets.keys() {
+        if !environment.contains_key(key) {
+            return Err(format!(
+                "Environment target set for unknown variable '{}'",
+                key
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn default_base_domain() -> String {
+    DEFAULT_BASE_DOMAIN.to_string()
+}
+
+fn default_dns_address() -> String {
+    DEFAULT_DNS_ADDRESS.to_string()
+}
+
+fn default_engine_socket() -> std::path::PathBuf {
+    std::path::PathBuf::from(DEFAULT_ENGINE_SOCKET)
+}
+
+// Nom d'hôte en minuscules : labels alphanumériques séparés par des points
+fn validate_hostname(hostname: &str) -> Result<(), String> {
+    let valid_label = |label: &str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    };
+    if hostname.len() <= 253 && hostname.contains('.') && hostname.split('.').all(valid_label) {
+        Ok(())
+    } else {
+        Err(format!("Invalid hostname '{}'", hostname))
+    }
+}
+
+// Un alias ne peut être servi que par un seul projet
+fn validate_aliases(state: &AppState, slug: &str, aliases: &[String]) -> Result<(), String> {
+    let primary = format!("{}.{}", slug, state.settings.base_domain);
+    for (index, alias) in aliases.iter().enumerate() {
+        validate_hostname(alias)?;
+        if aliases[..index].contains(alias) {
+            return Err(format!("Alias '{}' is listed twice", alias));
+        }
+        if *alias == primary {
+            return Err(format!(
+                "Alias '{}' is already the project's main hostname",
+                alias
+            ));
+        }
+        let owner = state
+            .projects
+            .values()
+            .find(|project| project.slug != slug && project.hostnames().contains(alias));
+        if let Some(owner) = owner {
+            return Err(format!(
+                "Alias '{}' is already used by project '{}'",
+                alias, owner.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Noms d'hôte réellement servis : projets et alias, services routés par Traefik
+fn required_hostnames(state: &AppState) -> Vec<String> {
+    docker::served_hostnames(&docker::build_compose_file(state))
+}
+
+// Avec le nouveau domaine, le nom principal d'un projet ne doit pas être
+// l'alias d'un projet, comme le vérifie validate_aliases
+fn validate_base_domain_change(state: &AppState, base_domain: &str) -> Result<(), String> {
+    for project in state.projects.values() {
+        let primary = format!("{}.{}", project.slug, base_domain);
+        let owner = state
+            .projects
+            .values()
+            .find(|other| other.aliases.contains(&primary));
+        if let Some(owner) = owner {
+            return Err(format!(
+                "Project '{}' would use '{}', which is already an alias of project '{}'",
+                project.name, primary, owner.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Les URL des projets et les règles Traefik des services suivent le domaine de base
+fn change_base_domain(state: &mut AppState, previous: &str, base_domain: &str) {
+    for project in state.projects.values_mut() {
+        project.url = generate_project_url(&project.slug, base_domain);
+    }
+    let previous_host = format!(".{}`", previous);
+    let host = format!(".{}`", base_domain);
+    for service in state.services.values_mut() {
+        for (key, value) in service.labels.iter_mut() {
+            if key.starts_with("traefik.http.routers.") && key.ends_with(".rule") {
+                *value = value.replace(&previous_host, &host);
+            }
+        }
+    }
+}
+
+fn validate_php_version(version: &str) -> Result<(), String> {
+    if SUPPORTED_PHP_VERSIONS.contains(&version) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unsupported PHP version '{}'. Available versions: {}",
+            version,
+            SUPPORTED_PHP_VERSIONS.join(", ")
+        ))
+    }
+}
 
 fn save_config(state: &AppState) -> Result<(), String> {
     let json = serde_json::to_string_pretty(state)
@@ -570,46 +1718,135 @@ fn load_config() -> Result<AppState, String> {
     let data = fs::read_to_string(&config_path)
         .map_err(|_| "No existing config, starting fresh".to_string())?;
 
-    serde_json::from_str(&data).map_err(|e| format!("Failed to parse config file: {}", e))
+    let mut state: AppState =
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse config file: {}", e))?;
+
+    if migrate_service_labels(&mut state) {
+        if let Err(e) = save_config(&state) {
+            eprintln!("Warning: Could not save migrated config: {}", e);
+        }
+    }
+
+    Ok(state)
+}
+
+// Les anciennes versions stockaient les labels Traefik dans `config`, qui est
+// émis comme variables d'environnement : on les déplace dans `labels`
+fn migrate_service_labels(state: &mut AppState) -> bool {
+    let mut migrated = false;
+    for service in state.services.values_mut() {
+        let label_keys: Vec<String> = service
+            .config
+            .keys()
+            .filter(|key| key.starts_with("traefik."))
+            .cloned()
+            .collect();
+
+        if label_keys.is_empty() {
+            continue;
+        }
+        for key in label_keys {
+            if let Some(value) = service.config.remove(&key) {
+                service.labels.entry(key).or_insert(value);
+            }
+        }
+        migrated = true;
+
+        // Les routeurs migrés passent en HTTPS comme ceux créés depuis le catalogue
+        let routers: Vec<String> = service
+            .labels
+            .keys()
+            .filter_map(|key| {
+                key.strip_prefix("traefik.http.routers.")
+                    .and_then(|rest| rest.strip_suffix(".rule"))
+                    .map(str::to_string)
+            })
+            .collect();
+        for router in routers {
+            service
+                .labels
+                .entry(format!("traefik.http.routers.{}.entrypoints", router))
+                .or_insert_with(|| "websecure".to_string());
+            service
+                .labels
+                .entry(format!("traefik.http.routers.{}.tls", router))
+                .or_insert_with(|| "true".to_string());
+        }
+    }
+    migrated
 }
 
-fn generate_project_url(project_name: &str) -> String {
-    format!("https://{}.{}", project_name, HTTPS_BASE_DOMAIN)
+fn generate_project_url(project_name: &str, base_domain: &str) -> String {
+    format!("https://{}.{}", project_name, base_domain)
 }
 
 struct AppStateWrapper(Mutex<AppState>);
 
+struct DnsServerWrapper(Mutex<Option<dns::DnsServer>>);
+
+struct RuntimeWrapper(Box<dyn ContainerRuntime>);
+
 pub fn run() -> tauri::App {
-    let app_state = load_config().unwrap_or_default();
+    let mut app_state = load_config().unwrap_or_default();
+    app_state.settings.engine_socket = app_state.settings.container_backend.socket_path();
+
+    let mut dns_server = None;
+    if let Err(e) = update_dns_server(&app_state.settings, &mut dns_server) {
+        eprintln!("{}", e);
+    }
+
     let state_wrapper = AppStateWrapper(Mutex::new(app_state));
 
     tauri::Builder::default()
         .plugin(tauri_plugin_localhost::Builder::new(1420).build())
         .manage(state_wrapper)
+        .manage(DnsServerWrapper(Mutex::new(dns_server)))
+        .manage(RuntimeWrapper(Box::new(runtime::SystemRuntime::default())))
         .invoke_handler(tauri::generate_handler![
             add_project,
             remove_project,
             update_project,
+            update_project_resources,
             add_service,
             remove_service,
             update_service,
             list_projects,
             list_services,
+            get_service_start_order,
             get_project_details,
             get_service_details,
             add_service_to_project,
             remove_service_from_project,
             generate_docker_compose,
             save_docker_compose,
+            diff_docker_compose,
+            get_compose_override,
+            save_compose_override,
+            validate_compose_overrides,
+            preview_compose_import,
+            apply_compose_import,
             start_environment,
+            check_port_conflicts,
             stop_environment,
+            start_project,
+            stop_project,
+            restart_project,
             check_docker_status,
             check_environment_status,
             setup_hosts_file,
             check_hosts_entries,
+            get_settings,
+            update_settings,
+            get_dns_status,
             generate_traefik_config,
+            install_local_ca,
+            uninstall_local_ca,
+            get_local_ca_status,
+            get_certificate_inventory,
+            renew_certificates,
             check_config_exists,
             list_predefined_services,
+            list_php_versions,
             add_predefined_service,
             reset_config,
             is_docker_installed,
@@ -624,3 +1861,234 @@ fn check_config_exists() -> Result<bool, String> {
     let docker_compose_path = system::get_docker_compose_dir()?.join("docker-compose.yml");
     Ok(docker_compose_path.exists())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtime::{RecordingRuntime, RuntimeCall};
+
+    #[test]
+    fn traefik_keys_move_from_config_to_labels() {
+        let data = r#"{
+            "services": {
+                "PhpMyAdmin": {
+                    "name": "PhpMyAdmin",
+                    "image": "phpmyadmin/phpmyadmin",
+                    "ports": [],
+                    "volumes": [],
+                    "global": true,
+                    "dependencies": [],
+                    "config": {
+                        "PMA_HOSTS": "mysql8",
+                        "traefik.enable": "true",
+                        "traefik.http.routers.phpmyadmin.rule": "Host(`phpmyadmin.local.test`)"
+                    }
+                }
+            },
+            "projects": {}
+        }"#;
+        let mut state: AppState = serde_json::from_str(data).unwrap();
+
+        assert!(migrate_service_labels(&mut state));
+        assert!(!migrate_service_labels(&mut state));
+
+        let service = &state.services["PhpMyAdmin"];
+        assert_eq!(service.config.len(), 1);
+        assert_eq!(service.config["PMA_HOSTS"], "mysql8");
+        assert_eq!(service.labels["traefik.enable"], "true");
+        assert_eq!(
+            service.labels["traefik.http.routers.phpmyadmin.tls"],
+            "true"
+        );
+        assert_eq!(
+            service.labels["traefik.http.routers.phpmyadmin.entrypoints"],
+            "websecure"
+        );
+        assert_eq!(
+            service.labels["traefik.http.routers.phpmyadmin.rule"],
+            "Host(`phpmyadmin.local.test`)"
+        );
+    }
+
+    #[test]
+    fn base_domain_change_moves_urls_and_rules() {
+        let mut state = AppState::default();
+        let project = Project::new("Shop".to_string(), DEFAULT_BASE_DOMAIN);
+        state.projects.insert(project.slug.clone(), project);
+        let service = get_predefined_services()
+            .into_iter()
+            .find(|service| service.name == "MailHog")
+            .unwrap()
+            .to_service(DEFAULT_BASE_DOMAIN);
+        state.services.insert(service.name.clone(), service);
+
+        let mut client = Project::new("Client".to_string(), DEFAULT_BASE_DOMAIN);
+        client.aliases = vec!["shop.dev.test".to_string()];
+        state.projects.insert(client.slug.clone(), client);
+        assert_eq!(
+            validate_base_domain_change(&state, "dev.test").unwrap_err(),
+            "Project 'Shop' would use 'shop.dev.test', which is already an alias of project 'Client'"
+        );
+        assert!(validate_base_domain_change(&state, "other.test").is_ok());
+        state.projects.remove("client");
+
+        change_base_domain(&mut state, DEFAULT_BASE_DOMAIN, "dev.test");
+
+        assert_eq!(state.projects["shop"].url, "https://shop.dev.test");
+        assert_eq!(
+            state.services["MailHog"].labels["traefik.http.routers.mailhog.rule"],
+            "Host(`mailhog.dev.test`)"
+        );
+        assert_eq!(
+            state.services["MailHog"].labels["traefik.http.routers.mailhog.entrypoints"],
+            "websecure"
+        );
+    }
+
+    #[test]
+    fn aliases_must_be_valid_and_unique() {
+        let mut state = AppState::default();
+        let mut project = Project::new("Client".to_string(), DEFAULT_BASE_DOMAIN);
+        project.aliases = vec!["client.test".to_string()];
+        state.projects.insert(project.slug.clone(), project);
+
+        assert!(validate_aliases(&state, "client", &["api.client.test".to_string()]).is_ok());
+        assert_eq!(
+            validate_aliases(&state, "client", &["client.local.test".to_string()]).unwrap_err(),
+            "Alias 'client.local.test' is already the project's main hostname"
+        );
+        assert_eq!(
+            validate_aliases(&state, "api", &["client.test".to_string()]).unwrap_err(),
+            "Alias 'client.test' is already used by project 'Client'"
+        );
+        assert_eq!(
+            validate_aliases(&state, "api", &["client.local.test".to_string()]).unwrap_err(),
+            "Alias 'client.local.test' is already used by project 'Client'"
+        );
+        assert_eq!(
+            validate_aliases(&state, "api", &["Not_Valid".to_string()]).unwrap_err(),
+            "Invalid hostname 'Not_Valid'"
+        );
+    }
+
+    #[test]
+    fn renewed_certificates_cover_every_served_hostname() {
+        let mut state = AppState::default();
+        let mut project = Project::new("Client".to_string(), DEFAULT_BASE_DOMAIN);
+        project.aliases = vec!["client.test".to_string()];
+        state.projects.insert(project.slug.clone(), project);
+        // Un service routé sur deux niveaux n'est pas couvert par le joker
+        let mut service = predefined_service("MailHog");
+        service.labels.insert(
+            "traefik.http.routers.mailhog.rule".to_string(),
+            "Host(`mail.tools.local.test`)".to_string(),
+        );
+        state.services.insert(service.name.clone(), service);
+
+        let dir = std::env::temp_dir().join(format!("dev-env-renewal-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let ca = certs::load_or_create_ca(&dir.join("ca")).unwrap();
+        let certs_dir = dir.join("certs");
+        let hostnames = required_hostnames(&state);
+        assert!(
+            certs::inspect_certificates(&certs_dir, &hostnames)
+                .unwrap()
+                .needs_renewal
+        );
+
+        certs::issue_certificates(&ca, &certs_dir, &docker::certificate_names(&state)).unwrap();
+        let inventory = certs::inspect_certificates(&certs_dir, &hostnames).unwrap();
+        assert!(inventory.uncovered.is_empty());
+        assert!(!inventory.needs_renewal);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn predefined_service(name: &str) -> Service {
+        get_predefined_services()
+            .into_iter()
+            .find(|service| service.name == name)
+            .unwrap()
+            .to_service(DEFAULT_BASE_DOMAIN)
+    }
+
+    #[test]
+    fn removing_a_service_stops_its_container() {
+        let mut state = AppState::default();
+        for name in ["mysql8", "PhpMyAdmin", "Redis"] {
+            state
+                .services
+                .insert(name.to_string(), predefined_service(name));
+        }
+        state.services.get_mut("PhpMyAdmin").unwrap().dependencies = vec!["mysql8".to_string()];
+        let mut project = Project::new("Shop".to_string(), DEFAULT_BASE_DOMAIN);
+        project.services = vec!["Redis".to_string(), "mysql8".to_string()];
+        state.projects.insert(project.slug.clone(), project);
+
+        // PhpMyAdmin dépend de mysql8 : rien ne doit être arrêté
+        let runtime = RecordingRuntime::running();
+        assert!(remove_service_and_container(&mut state, &runtime, "mysql8").is_err());
+        assert!(runtime.calls().is_empty());
+
+        remove_service_and_container(&mut state, &runtime, "Redis").unwrap();
+        assert_eq!(
+            runtime.calls(),
+            vec![
+                RuntimeCall::Compose(vec!["stop".to_string(), "Redis".to_string()]),
+                RuntimeCall::Compose(vec![
+                    "rm".to_string(),
+                    "-f".to_string(),
+                    "Redis".to_string()
+                ]),
+            ]
+        );
+        assert!(!state.services.contains_key("Redis"));
+        assert_eq!(state.projects["shop"].services, vec!["mysql8".to_string()]);
+    }
+
+    #[test]
+    fn imported_project_gets_its_slug_and_url_from_its_name() {
+        let state = AppState::default();
+        let mut crafted = Project::new("Shop".to_string(), DEFAULT_BASE_DOMAIN);
+        crafted.slug = "../../etc".to_string();
+        crafted.url = "https://shop.elsewhere.test".to_string();
+
+        let project = imported_project(&state, crafted.clone()).unwrap();
+        assert_eq!(project.slug, "shop");
+        assert_eq!(project.url, "https://shop.local.test");
+
+        crafted.resources.memory_limit = Some("lots".to_string());
+        assert!(imported_project(&state, crafted).is_err());
+    }
+
+    #[test]
+    fn failed_container_removal_still_removes_the_service() {
+        let mut state = AppState::default();
+        state
+            .services
+            .insert("Redis".to_string(), predefined_service("Redis"));
+
+        // Conteneur jamais créé : compose échoue, le service est quand même retiré
+        let mut runtime = RecordingRuntime::running();
+        runtime.compose_error = Some("no such service: Redis".to_string());
+        remove_service_and_container(&mut state, &runtime, "Redis").unwrap();
+        assert_eq!(
+            runtime.calls(),
+            vec![RuntimeCall::Compose(vec![
+                "stop".to_string(),
+                "Redis".to_string()
+            ])]
+        );
+        assert!(state.services.is_empty());
+    }
+
+    #[test]
+    fn start_fails_cleanly_when_docker_is_down() {
+        let runtime = RecordingRuntime::default();
+        assert_eq!(
+            start_environment_with(&AppState::default(), &runtime).unwrap_err(),
+            "Docker is not running. Please start it first."
+        );
+        assert_eq!(runtime.calls(), vec![RuntimeCall::IsRunning]);
+    }
+}
@@ -4,15 +4,7 @@ use std::panic;
 use std::sync::Arc;
 
 fn stop_environment() -> std::io::Result<()> {
-    let docker_compose_dir = dev_env_lib::system::get_docker_compose_dir()
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    
-    std::process::Command::new("docker-compose")
-        .args(&["down"])
-        .current_dir(&docker_compose_dir)
-        .status()?;
-    
-    Ok(())
+    dev_env_lib::shutdown_environment().map_err(std::io::Error::other)
 }
 
 fn main() {
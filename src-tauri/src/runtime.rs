@@ -0,0 +1,199 @@
+use std::sync::Mutex;
+
+use crate::backend::ContainerBackend;
+use crate::compose_cli::{ComposeInvoker, ComposeProvider};
+use crate::system;
+use crate::Settings;
+
+// Point de passage unique des commandes vers le moteur de conteneurs et l'outil
+// compose. L'implémentation réelle est gardée dans l'état Tauri ; les tests
+// utilisent RecordingRuntime, qui n'exécute rien.
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub trait ContainerRuntime: Send + Sync {
+    fn is_installed(&self, backend: ContainerBackend) -> Result<bool, String>;
+    fn is_running(&self, backend: ContainerBackend) -> Result<bool, String>;
+    fn is_environment_running(&self, backend: ContainerBackend) -> Result<bool, String>;
+    // Ports hôte (port, protocole) déjà publiés par l'environnement
+    fn environment_ports(&self, backend: ContainerBackend) -> Result<Vec<(u16, String)>, String>;
+    // Lance l'outil compose sur les fichiers de l'environnement
+    fn compose(&self, settings: &Settings, args: &[String]) -> Result<CommandOutput, String>;
+    // Oublie l'outil compose détecté, après un changement de moteur ou d'ordre
+    fn reset_compose(&self);
+    // Version du moteur : son API, ou sa CLI si le démon ne répond pas
+    fn version(&self, backend: ContainerBackend) -> Option<String>;
+    // Outil compose utilisé avec ces réglages
+    fn compose_info(&self, settings: &Settings) -> Result<ComposeInvoker, String>;
+}
+
+// Ordre de détection configuré, ou celui du moteur par défaut
+fn compose_order(settings: &Settings) -> Vec<ComposeProvider> {
+    if settings.compose_providers.is_empty() {
+        settings.container_backend.compose_order()
+    } else {
+        settings.compose_providers.clone()
+    }
+}
+
+// La détection lance chaque outil candidat : elle est faite une fois, puis
+// gardée jusqu'au prochain changement de réglages
+#[derive(Default)]
+pub struct SystemRuntime {
+    compose: Mutex<Option<ComposeInvoker>>,
+}
+
+impl SystemRuntime {
+    // Outil compose choisi selon les réglages et branché sur la socket du moteur
+    fn compose_invoker(&self, settings: &Settings) -> Result<ComposeInvoker, String> {
+        let mut detected = self.compose.lock().unwrap();
+        let invoker = match detected.as_ref() {
+            Some(invoker) => invoker.clone(),
+            None => detected
+                .insert(ComposeInvoker::detect(&compose_order(settings))?)
+                .clone(),
+        };
+        // La socket Podman peut apparaître après la détection
+        Ok(invoker.with_docker_host(settings.container_backend.docker_host()))
+    }
+}
+
+impl ContainerRuntime for SystemRuntime {
+    fn is_installed(&self, backend: ContainerBackend) -> Result<bool, String> {
+        system::is_docker_installed(backend)
+    }
+
+    fn is_running(&self, backend: ContainerBackend) -> Result<bool, String> {
+        system::is_docker_running(backend)
+    }
+
+    fn is_environment_running(&self, backend: ContainerBackend) -> Result<bool, String> {
+        system::is_environment_running(backend)
+    }
+
+    fn environment_ports(&self, backend: ContainerBackend) -> Result<Vec<(u16, String)>, String> {
+        system::environment_ports(backend)
+    }
+
+    fn compose(&self, settings: &Settings, args: &[String]) -> Result<CommandOutput, String> {
+        let output = self.compose_invoker(settings)?.run(args)?;
+        Ok(CommandOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    fn reset_compose(&self) {
+        *self.compose.lock().unwrap() = None;
+    }
+
+    fn version(&self, backend: ContainerBackend) -> Option<String> {
+        system::engine_version(backend)
+    }
+
+    fn compose_info(&self, settings: &Settings) -> Result<ComposeInvoker, String> {
+        self.compose_invoker(settings)
+    }
+}
+
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeCall {
+    IsInstalled,
+    IsRunning,
+    IsEnvironmentRunning,
+    EnvironmentPorts,
+    Compose(Vec<String>),
+    ResetCompose,
+    Version,
+    ComposeInfo,
+}
+
+// Moteur simulé : répond selon ses champs et enregistre chaque appel
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingRuntime {
+    pub installed: bool,
+    pub running: bool,
+    pub environment_running: bool,
+    pub environment_ports: Vec<(u16, String)>,
+    // Sortie d'erreur des appels compose ; None = succès
+    pub compose_error: Option<String>,
+    calls: std::sync::Mutex<Vec<RuntimeCall>>,
+}
+
+#[cfg(test)]
+impl RecordingRuntime {
+    pub fn running() -> RecordingRuntime {
+        RecordingRuntime {
+            installed: true,
+            running: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn calls(&self) -> Vec<RuntimeCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: RuntimeCall) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+#[cfg(test)]
+impl ContainerRuntime for RecordingRuntime {
+    fn is_installed(&self, _: ContainerBackend) -> Result<bool, String> {
+        self.record(RuntimeCall::IsInstalled);
+        Ok(self.installed)
+    }
+
+    fn is_running(&self, _: ContainerBackend) -> Result<bool, String> {
+        self.record(RuntimeCall::IsRunning);
+        Ok(self.running)
+    }
+
+    fn is_environment_running(&self, _: ContainerBackend) -> Result<bool, String> {
+        self.record(RuntimeCall::IsEnvironmentRunning);
+        Ok(self.environment_running)
+    }
+
+    fn environment_ports(&self, _: ContainerBackend) -> Result<Vec<(u16, String)>, String> {
+        self.record(RuntimeCall::EnvironmentPorts);
+        Ok(self.environment_ports.clone())
+    }
+
+    fn compose(&self, _: &Settings, args: &[String]) -> Result<CommandOutput, String> {
+        self.record(RuntimeCall::Compose(args.to_vec()));
+        Ok(CommandOutput {
+            success: self.compose_error.is_none(),
+            stdout: String::new(),
+            stderr: self.compose_error.clone().unwrap_or_default(),
+        })
+    }
+
+    fn reset_compose(&self) {
+        self.record(RuntimeCall::ResetCompose);
+    }
+
+    fn version(&self, backend: ContainerBackend) -> Option<String> {
+        self.record(RuntimeCall::Version);
+        self.installed
+            .then(|| format!("{} version test", backend.label()))
+    }
+
+    fn compose_info(&self, _: &Settings) -> Result<ComposeInvoker, String> {
+        self.record(RuntimeCall::ComposeInfo);
+        Ok(ComposeInvoker {
+            provider: ComposeProvider::DockerPlugin,
+            version: "Docker Compose version test".to_string(),
+            docker_host: None,
+        })
+    }
+}
@@ -1,8 +1,20 @@
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::compose::ComposeFile;
+use crate::backend::ContainerBackend;
+use crate::compose_cli::{ComposeInvoker, ComposeProvider};
+use crate::docker_api::ContainerSummary;
+
+pub const COMPOSE_FILE: &str = "docker-compose.yml";
+pub const COMPOSE_OVERRIDE_FILE: &str = "docker-compose.override.yml";
+// Copie du dernier docker-compose.yml enregistré, pour repérer les modifications manuelles
+pub const GENERATED_COMPOSE_FILE: &str = ".docker-compose.generated.yml";
+const COMPOSE_OVERRIDES_DIR: &str = "overrides";
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SystemInfo {
@@ -13,6 +25,8 @@ pub struct SystemInfo {
     memory_total: u64,
     docker_version: Option<String>,
     docker_compose_version: Option<String>,
+    compose_provider: Option<ComposeProvider>,
+    container_backend: ContainerBackend,
 }
 
 pub fn normalize_slug(input: &str) -> String {
@@ -90,148 +104,564 @@ pub fn get_docker_compose_dir() -> Result<PathBuf, String> {
     Ok(docker_dir)
 }
 
+// Exécutable présent dans le PATH
+pub fn command_exists(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+// Lance une commande en root : pkexec affiche une demande graphique,
+// sudo sert de repli hors session graphique
+pub fn run_privileged(program: &str, args: &[&str]) -> Result<(), String> {
+    run_privileged_with_input(program, args, None)
+}
+
+// Variante qui passe `input` sur l'entrée standard, sans fichier intermédiaire
+pub fn run_privileged_with_input(
+    program: &str,
+    args: &[&str],
+    input: Option<&str>,
+) -> Result<(), String> {
+    let launcher = if command_exists("pkexec") {
+        "pkexec"
+    } else if command_exists("sudo") {
+        "sudo"
+    } else {
+        return Err("Neither pkexec nor sudo is available".to_string());
+    };
+
+    let stdin = match input {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    };
+    let mut child = Command::new(launcher)
+        .arg(program)
+        .args(args)
+        .stdin(stdin)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    // L'entrée est fermée avant l'attente, pour que le programme voie sa fin
+    let written = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => stdin.write_all(input.as_bytes()),
+        _ => Ok(()),
+    };
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to run {}: {}", program, error.trim()));
+    }
+    written.map_err(|e| format!("Failed to send input to {}: {}", program, e))
+}
+
+// Fichier d'override global ou propre à un projet, fusionné sur le compose généré
+pub fn get_compose_override_path(project_slug: Option<&str>) -> Result<PathBuf, String> {
+    let docker_dir = get_docker_compose_dir()?;
+    Ok(match project_slug {
+        Some(slug) => docker_dir
+            .join(COMPOSE_OVERRIDES_DIR)
+            .join(format!("{}.yml", slug)),
+        None => docker_dir.join(COMPOSE_OVERRIDE_FILE),
+    })
+}
+
+// Fichiers compose existants, dans l'ordre où docker-compose doit les fusionner :
+// le fichier généré, l'override global puis les overrides des projets
+pub fn get_compose_files() -> Result<Vec<PathBuf>, String> {
+    let docker_dir = get_docker_compose_dir()?;
+    let mut files = vec![docker_dir.join(COMPOSE_FILE)];
+
+    let global_override = get_compose_override_path(None)?;
+    if global_override.exists() {
+        files.push(global_override);
+    }
+
+    let overrides_dir = docker_dir.join(COMPOSE_OVERRIDES_DIR);
+    if overrides_dir.exists() {
+        let mut project_overrides: Vec<PathBuf> = fs::read_dir(&overrides_dir)
+            .map_err(|e| format!("Failed to read overrides directory: {}", e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "yml"))
+            .collect();
+        project_overrides.sort();
+        files.extend(project_overrides);
+    }
+
+    Ok(files)
+}
+
+// Arguments `-f <fichier>` à passer à chaque appel de docker-compose
+pub fn compose_file_args() -> Result<Vec<String>, String> {
+    Ok(get_compose_files()?
+        .into_iter()
+        .flat_map(|file| ["-f".to_string(), file.display().to_string()])
+        .collect())
+}
+
 // Check if Docker is running
-pub fn is_docker_running() -> Result<bool, String> {
+pub fn is_docker_running(backend: ContainerBackend) -> Result<bool, String> {
     #[cfg(target_os = "windows")]
     {
         let output = Command::new("cmd")
-            .args(["/C", "docker info"])
+            .args(["/C", &format!("{} info", backend.cli())])
             .output()
-            .map_err(|e| format!("Failed to check Docker status: {}", e))?;
+            .map_err(|e| format!("Failed to check {} status: {}", backend.label(), e))?;
         Ok(output.status.success())
     }
     #[cfg(not(target_os = "windows"))]
     {
-        let output = Command::new("sh")
-            .args(["-c", "docker info >/dev/null 2>&1"])
-            .status()
-            .map_err(|e| format!("Failed to check Docker status: {}", e))?;
-        Ok(output.success())
+        // Un démon injoignable n'est pas une erreur : le moteur est simplement arrêté
+        Ok(backend.is_running())
     }
 }
 
-// Check if environment is running
-pub fn is_environment_running() -> Result<bool, String> {
-    let docker_compose_path = get_docker_compose_dir()?.join("docker-compose.yml");
+// Conteneurs démarrés par compose depuis le dossier de l'environnement
+fn environment_containers(backend: ContainerBackend) -> Result<Vec<ContainerSummary>, String> {
+    let docker_compose_path = get_docker_compose_dir()?.join(COMPOSE_FILE);
 
     if !docker_compose_path.exists() {
-        return Ok(false);
+        return Ok(Vec::new());
     }
 
-    #[cfg(target_os = "windows")]
-    {
-        let output = Command::new("cmd")
-            .args(["/C", "docker-compose ps -q"])
-            .current_dir(get_docker_compose_dir()?)
-            .output()
-            .map_err(|e| format!("Failed to check environment status: {}", e))?;
+    let working_dir = get_docker_compose_dir()?;
+    let filter = format!(
+        "com.docker.compose.project.working_dir={}",
+        working_dir.display()
+    );
+    backend
+        .client()?
+        .containers(false, &[filter])
+        .map_err(|e| format!("Failed to check environment status: {}", e))
+}
+
+// Check if environment is running
+pub fn is_environment_running(backend: ContainerBackend) -> Result<bool, String> {
+    Ok(!environment_containers(backend)?.is_empty())
+}
 
-        Ok(!output.stdout.is_empty())
+// Ports hôte publiés par les conteneurs de l'environnement qui tournent
+pub fn environment_ports(backend: ContainerBackend) -> Result<Vec<(u16, String)>, String> {
+    Ok(environment_containers(backend)?
+        .iter()
+        .flat_map(|container| &container.ports)
+        .filter_map(|port| Some((port.public_port?, port.protocol.clone())))
+        .collect())
+}
+
+// Port hôte déjà utilisé par un autre processus
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PortConflict {
+    pub port: u16,
+    pub protocol: String,
+    pub service: String,
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+}
+
+// Sockets (port, inode) d'un fichier /proc/net/{tcp,tcp6,udp,udp6}.
+// En TCP seuls les sockets en écoute (état 0A) occupent le port.
+fn parse_proc_net(content: &str, tcp: bool) -> Vec<(u16, u64)> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let local_port = fields.get(1)?.rsplit(':').next()?;
+            let state = fields.get(3)?;
+            let inode = fields.get(9)?.parse::<u64>().ok()?;
+            if tcp && *state != "0A" {
+                return None;
+            }
+            Some((u16::from_str_radix(local_port, 16).ok()?, inode))
+        })
+        .collect()
+}
+
+// Processus propriétaire d'un socket, si /proc/<pid>/fd est lisible
+fn find_socket_owner(inode: u64) -> Option<(u32, String)> {
+    let target = format!("socket:[{}]", inode);
+    for entry in fs::read_dir("/proc").ok()?.flatten() {
+        let pid = match entry.file_name().to_string_lossy().parse::<u32>() {
+            Ok(pid) => pid,
+            Err(_) => continue,
+        };
+        let fds = match fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        let owns_socket = fds
+            .flatten()
+            .filter_map(|fd| fs::read_link(fd.path()).ok())
+            .any(|link| link.to_string_lossy() == target);
+        if owns_socket {
+            let process = fs::read_to_string(entry.path().join("comm"))
+                .map(|comm| comm.trim().to_string())
+                .unwrap_or_default();
+            return Some((pid, process));
+        }
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        let output = Command::new("sh")
-            .args(["-c", "docker-compose ps -q"])
-            .current_dir(get_docker_compose_dir()?)
-            .output()
-            .map_err(|e| format!("Failed to check environment status: {}", e))?;
+    None
+}
 
-        Ok(!output.stdout.is_empty())
+// Sockets occupés d'un protocole, lus depuis /proc/net quand il existe
+fn used_ports(protocol: &str) -> Option<Vec<(u16, u64)>> {
+    let tcp = protocol == "tcp";
+    let mut ports = Vec::new();
+    let mut found = false;
+    for file in [protocol.to_string(), format!("{}6", protocol)] {
+        if let Ok(content) = fs::read_to_string(format!("/proc/net/{}", file)) {
+            found = true;
+            ports.extend(parse_proc_net(&content, tcp));
+        }
     }
+    found.then_some(ports)
 }
 
-// Setup local hosts file entries
-pub fn setup_hosts(base_domain: &str) -> Result<(), String> {
-    // This is a privileged operation and may require special handling
-    #[cfg(target_os = "windows")]
-    {
-        // Path to Windows hosts file
-        let hosts_path = PathBuf::from("C:\\Windows\\System32\\drivers\\etc\\hosts");
-        update_hosts_file(hosts_path, base_domain)?;
+// Sans /proc, on tente de réserver le port pour savoir s'il est libre
+fn is_port_free(port: u16, protocol: &str) -> bool {
+    if protocol == "udp" {
+        std::net::UdpSocket::bind(("0.0.0.0", port)).is_ok()
+    } else {
+        std::net::TcpListener::bind(("0.0.0.0", port)).is_ok()
     }
+}
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        // Path to Unix hosts file
-        let hosts_path = PathBuf::from("/etc/hosts");
-        update_hosts_file(hosts_path, base_domain)?;
+// Vérifie que chaque port publié par le compose est libre sur l'hôte, hors
+// ports déjà tenus par les conteneurs de l'environnement
+pub fn check_port_conflicts(compose: &ComposeFile, owned: &[(u16, String)]) -> Vec<PortConflict> {
+    let mut conflicts = Vec::new();
+    let mut proc_ports: HashMap<String, Option<Vec<(u16, u64)>>> = HashMap::new();
+
+    for published in compose.published_ports() {
+        if owned
+            .iter()
+            .any(|(port, protocol)| *port == published.port && *protocol == published.protocol)
+        {
+            continue;
+        }
+        let used = proc_ports
+            .entry(published.protocol.clone())
+            .or_insert_with(|| used_ports(&published.protocol));
+
+        let conflict = match used {
+            Some(sockets) => sockets
+                .iter()
+                .find(|(port, _)| *port == published.port)
+                .map(|(_, inode)| find_socket_owner(*inode)),
+            None => (!is_port_free(published.port, &published.protocol)).then_some(None),
+        };
+
+        if let Some(owner) = conflict {
+            conflicts.push(PortConflict {
+                port: published.port,
+                protocol: published.protocol,
+                service: published.service,
+                pid: owner.as_ref().map(|(pid, _)| *pid),
+                process: owner.map(|(_, process)| process),
+            });
+        }
     }
 
-    Ok(())
+    conflicts
 }
 
-fn update_hosts_file(hosts_path: PathBuf, base_domain: &str) -> Result<(), String> {
-    // Read the current hosts file content
-    let hosts_content =
-        fs::read_to_string(&hosts_path).map_err(|e| format!("Failed to read hosts file: {}", e))?;
+// Une entrée 127.0.0.1 par nom d'hôte servi
+fn hosts_entries(hostnames: &[String]) -> Vec<String> {
+    hostnames
+        .iter()
+        .map(|hostname| format!("127.0.0.1 {}", hostname))
+        .collect()
+}
 
-    // Entries we want to ensure are present
-    let required_entries = vec![
-        format!("127.0.0.1 {}", base_domain),
-        format!("127.0.0.1 traefik.{}", base_domain),
-        format!("127.0.0.1 pma.{}", base_domain),
-        format!("127.0.0.1 mail.{}", base_domain),
-    ];
+// Le nom est résolu vers une adresse locale par une ligne du fichier hosts,
+// quel que soit son format (alignement, plusieurs noms, commentaire en fin de ligne)
+fn hosts_file_resolves(content: &str, hostname: &str) -> bool {
+    content.lines().any(|line| {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let is_loopback = fields
+            .next()
+            .and_then(|address| address.parse::<std::net::IpAddr>().ok())
+            .map(|address| address.is_loopback())
+            .unwrap_or(false);
+        is_loopback && fields.any(|name| name.eq_ignore_ascii_case(hostname))
+    })
+}
+
+const HOSTS_BLOCK_BEGIN: &str = "# BEGIN dev-env";
+const HOSTS_BLOCK_END: &str = "# END dev-env";
+
+// Résultat d'une mise à jour du fichier hosts ; en dry-run rien n'est écrit
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostsUpdate {
+    pub path: String,
+    // Lignes retirées ("-...") et ajoutées ("+...") dans l'ordre du fichier
+    pub diff: Vec<String>,
+    pub changed: bool,
+    pub applied: bool,
+}
 
-    // Check if entries are already present
-    let mut missing_entries = Vec::new();
-    for entry in &required_entries {
-        if !hosts_content.contains(entry) {
-            missing_entries.push(entry.clone());
+pub fn hosts_path() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        PathBuf::from("C:\\Windows\\System32\\drivers\\etc\\hosts")
+    } else {
+        PathBuf::from("/etc/hosts")
+    }
+}
+
+pub fn has_hosts_block(content: &str) -> bool {
+    content.lines().any(|line| line.trim() == HOSTS_BLOCK_BEGIN)
+}
+
+// Remplace le bloc dev-env (ou l'ajoute en fin de fichier) ; sans entrées, le bloc
+// est retiré. Les lignes hors du bloc ne sont jamais modifiées, fins de ligne
+// comprises : un bloc mal formé (marqueur manquant ou dans le désordre) est refusé
+// plutôt que de tronquer le fichier, et un bloc identique laisse le fichier tel quel.
+pub fn replace_hosts_block(content: &str, entries: &[String]) -> Result<String, String> {
+    let malformed = || {
+        format!(
+            "The dev-env block of the hosts file is malformed: check its '{}' and '{}' lines",
+            HOSTS_BLOCK_BEGIN, HOSTS_BLOCK_END
+        )
+    };
+    let mut lines: Vec<&str> = Vec::new();
+    let mut current = Vec::new();
+    let mut block_at = None;
+    let mut in_block = false;
+    for line in content.split_inclusive('\n') {
+        match line.trim() {
+            HOSTS_BLOCK_BEGIN if in_block => return Err(malformed()),
+            HOSTS_BLOCK_BEGIN => {
+                in_block = true;
+                block_at.get_or_insert(lines.len());
+                current.push(HOSTS_BLOCK_BEGIN);
+            }
+            HOSTS_BLOCK_END if in_block => {
+                in_block = false;
+                current.push(HOSTS_BLOCK_END);
+            }
+            HOSTS_BLOCK_END => return Err(malformed()),
+            entry if in_block => current.push(entry),
+            _ => lines.push(line),
         }
     }
+    if in_block {
+        return Err(malformed());
+    }
 
-    if missing_entries.is_empty() {
-        return Ok(());
+    let mut block = Vec::new();
+    if !entries.is_empty() {
+        block.push(HOSTS_BLOCK_BEGIN);
+        block.extend(entries.iter().map(String::as_str));
+        block.push(HOSTS_BLOCK_END);
+    }
+    if block == current {
+        return Ok(content.to_string());
     }
 
-    // Inform the user about required entries
-    let message = format!(
-        "The following entries should be added to your hosts file ({}):
-{}
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let at = block_at.unwrap_or(lines.len());
+    let mut updated = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if index == at {
+            updated.extend(block.iter().flat_map(|line| [*line, eol]));
+        }
+        updated.push_str(line);
+    }
+    if at == lines.len() && !block.is_empty() {
+        // Le bloc ajouté en fin de fichier commence sur sa propre ligne
+        if !updated.is_empty() && !updated.ends_with('\n') {
+            updated.push_str(eol);
+        }
+        updated.extend(block.iter().flat_map(|line| [*line, eol]));
+    }
+    Ok(updated)
+}
 
-This typically requires administrator/root privileges.",
-        hosts_path.display(),
-        missing_entries.join("\n")
-    );
+// Diff ligne à ligne par plus longue sous-séquence commune
+pub fn line_diff(before: &str, after: &str) -> Vec<String> {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+
+    let mut common = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            i += 1;
+            j += 1;
+        } else if j < after.len() && (i == before.len() || common[i][j + 1] > common[i + 1][j]) {
+            diff.push(format!("+{}", after[j]));
+            j += 1;
+        } else {
+            diff.push(format!("-{}", before[i]));
+            i += 1;
+        }
+    }
+    diff
+}
 
-    // In a real app, you might want to handle this differently
-    // For example, by opening an elevated process on Windows or using sudo on Unix
-    eprintln!("{}", message);
+// Écrit le fichier hosts, via pkexec/sudo quand l'utilisateur n'a pas les droits
+fn write_hosts_file(hosts_path: &Path, content: &str) -> Result<(), String> {
+    match fs::write(hosts_path, content) {
+        Ok(()) => Ok(()),
+        // Le contenu passe par l'entrée de tee : pas de fichier temporaire qu'un autre
+        // utilisateur pourrait remplacer, et tee garde le propriétaire et les droits
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && cfg!(unix) => {
+            run_privileged_with_input("tee", &[&hosts_path.to_string_lossy()], Some(content))
+        }
+        Err(e) => Err(format!("Failed to write hosts file: {}", e)),
+    }
+}
+
+// Met le bloc dev-env du fichier hosts à jour avec `entries`
+pub fn update_hosts_file(
+    hosts_path: &Path,
+    entries: &[String],
+    dry_run: bool,
+) -> Result<HostsUpdate, String> {
+    let hosts_content =
+        fs::read_to_string(hosts_path).map_err(|e| format!("Failed to read hosts file: {}", e))?;
+    let updated = replace_hosts_block(&hosts_content, entries)?;
+    let changed = updated != hosts_content;
+
+    if changed && !dry_run {
+        write_hosts_file(hosts_path, &updated)?;
+    }
+
+    Ok(HostsUpdate {
+        path: hosts_path.display().to_string(),
+        diff: line_diff(&hosts_content, &updated),
+        changed,
+        applied: changed && !dry_run,
+    })
+}
+
+// Setup local hosts file entries
+pub fn setup_hosts(hostnames: &[String], dry_run: bool) -> Result<HostsUpdate, String> {
+    update_hosts_file(&hosts_path(), &hosts_entries(hostnames), dry_run)
+}
 
+// Suit les ajouts et suppressions de projets, seulement si l'utilisateur a déjà
+// installé le bloc dev-env : pas de demande d'élévation sinon
+pub fn sync_hosts(hostnames: &[String]) -> Result<(), String> {
+    let hosts_path = hosts_path();
+    let hosts_content = fs::read_to_string(&hosts_path).unwrap_or_default();
+    if has_hosts_block(&hosts_content) {
+        setup_hosts(hostnames, false)?;
+    }
     Ok(())
 }
 
 // Vérifier les entrées dans le fichier hosts
-pub fn check_hosts_entries(base_domain: &str) -> Result<Vec<(String, bool)>, String> {
-    #[cfg(target_os = "windows")]
-    let hosts_path = std::path::PathBuf::from("C:\\Windows\\System32\\drivers\\etc\\hosts");
-
-    #[cfg(not(target_os = "windows"))]
-    let hosts_path = std::path::PathBuf::from("/etc/hosts");
+pub fn check_hosts_entries(hostnames: &[String]) -> Result<Vec<(String, bool)>, String> {
+    let hosts_path = hosts_path();
 
     // Lire le contenu actuel du fichier hosts
     let hosts_content =
         fs::read_to_string(&hosts_path).map_err(|e| format!("Failed to read hosts file: {}", e))?;
 
-    // Les entrées que nous voulons vérifier
-    let required_entries = vec![
-        format!("127.0.0.1 {}", base_domain),
-        format!("127.0.0.1 traefik.{}", base_domain),
-        format!("127.0.0.1 pma.{}", base_domain),
-        format!("127.0.0.1 mail.{}", base_domain),
-    ];
-
     // Vérifier quelles entrées sont présentes et lesquelles sont absentes
-    let mut status: Vec<(String, bool)> = Vec::new();
-    for entry in required_entries {
-        let present = hosts_content.contains(&entry);
-        status.push((entry, present));
-    }
+    let status = hostnames
+        .iter()
+        .zip(hosts_entries(hostnames))
+        .map(|(hostname, entry)| (entry, hosts_file_resolves(&hosts_content, hostname)))
+        .collect();
 
     Ok(status)
 }
 
+// Redirection du domaine de base vers le serveur DNS de dev-env
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DnsForwarding {
+    pub systemd_resolved: bool,
+    pub network_manager: bool,
+    // Configurations à installer quand aucune redirection n'est en place
+    pub resolved_config: String,
+    pub dnsmasq_config: String,
+}
+
+// Contenu des fichiers .conf d'un dossier, triés par nom
+fn read_conf_dir(dir: &str) -> Vec<String> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    files.retain(|path| path.extension().map(|ext| ext == "conf").unwrap_or(false));
+    files.sort();
+    files
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .collect()
+}
+
+// systemd-resolved : "DNS=127.0.0.1:5300" et "Domains=~local.test" dans une
+// même section [Resolve]
+fn resolved_forwards(configs: &[String], base_domain: &str, address: &str) -> bool {
+    configs.iter().any(|config| {
+        let mut dns = false;
+        let mut domain = false;
+        for line in config.lines().map(str::trim) {
+            if let Some(servers) = line.strip_prefix("DNS=") {
+                dns |= servers.split_whitespace().any(|server| server == address);
+            } else if let Some(domains) = line.strip_prefix("Domains=") {
+                domain |= domains
+                    .split_whitespace()
+                    .any(|entry| entry.trim_start_matches('~') == base_domain);
+            }
+        }
+        dns && domain
+    })
+}
+
+// NetworkManager avec dnsmasq : "server=/local.test/127.0.0.1#5300"
+fn dnsmasq_forwards(configs: &[String], base_domain: &str, address: &str) -> bool {
+    let expected = format!("server=/{}/{}", base_domain, address.replacen(':', "#", 1));
+    configs
+        .iter()
+        .any(|config| config.lines().any(|line| line.trim() == expected))
+}
+
+pub fn dns_forwarding_status(base_domain: &str, address: &str) -> DnsForwarding {
+    let mut resolved_configs: Vec<String> = fs::read_to_string("/etc/systemd/resolved.conf")
+        .into_iter()
+        .collect();
+    resolved_configs.extend(read_conf_dir("/etc/systemd/resolved.conf.d"));
+    let resolved_active = PathBuf::from("/run/systemd/resolve").exists();
+
+    let network_manager_configs = read_conf_dir("/etc/NetworkManager/dnsmasq.d");
+
+    DnsForwarding {
+        systemd_resolved: resolved_active
+            && resolved_forwards(&resolved_configs, base_domain, address),
+        network_manager: dnsmasq_forwards(&network_manager_configs, base_domain, address),
+        resolved_config: format!("[Resolve]\nDNS={}\nDomains=~{}\n", address, base_domain),
+        dnsmasq_config: format!(
+            "server=/{}/{}\n",
+            base_domain,
+            address.replacen(':', "#", 1)
+        ),
+    }
+}
+
 // Create a project directory
 pub fn create_project_dir(project_name: &str) -> Result<PathBuf, String> {
     let normalized_name = normalize_slug(project_name);
@@ -300,65 +730,48 @@ pub fn create_nginx_config(project_name: &str) -> Result<PathBuf, String> {
     Ok(config_path)
 }
 
-pub fn is_docker_installed() -> Result<bool, String> {
-    #[cfg(target_os = "windows")]
-    {
-        let output = Command::new("where")
-            .args(["docker"])
-            .output()
-            .map_err(|e| format!("Failed to check docker installation: {}", e))?;
-        
-        Ok(output.status.success())
-    }
-    #[cfg(target_os = "macos")]
-    {
-        let output = Command::new("sh")
-            .args(["-c", "which docker"])
-            .output()
-            .map_err(|e| format!("Failed to check docker installation: {}", e))?;
-        
-        Ok(output.status.success())
-    }
-    #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
-    {
-        let output = Command::new("sh")
-            .args(["-c", "which docker"])
-            .output()
-            .map_err(|e| format!("Failed to check docker installation: {}", e))?;
-        
-        Ok(output.status.success())
-    }
+pub fn is_docker_installed(backend: ContainerBackend) -> Result<bool, String> {
+    Ok(backend.is_installed())
 }
 
-pub fn restart_environment(_: &str) -> Result<(), String> {
-    let docker_compose_dir = get_docker_compose_dir()?;
-
-    // Stop the environment first
-    std::process::Command::new("docker-compose")
-        .args(["down"])
-        .current_dir(&docker_compose_dir)
-        .output()
-        .map_err(|e| format!("Failed to stop environment: {}", e))?;
-
-    // Start the environment
-    std::process::Command::new("docker-compose")
-        .args(["up", "-d"])
-        .current_dir(&docker_compose_dir)
-        .output()
-        .map_err(|e| format!("Failed to start environment: {}", e))?;
+// `docker --version` ou `podman --version`, sans passer par le démon
+fn cli_version(backend: ContainerBackend) -> Option<String> {
+    let output = Command::new(backend.cli()).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
 
-    Ok(())
+// Version du moteur, demandée à son API ; la CLI répond aussi démon arrêté
+pub fn engine_version(backend: ContainerBackend) -> Option<String> {
+    match backend.client().and_then(|client| client.version()) {
+        Ok(version) => Some(format!(
+            "{} version {} (API {})",
+            backend.label(),
+            version.version,
+            version.api_version
+        )),
+        Err(_) => cli_version(backend),
+    }
 }
 
-pub fn get_system_info() -> Result<SystemInfo, String> {
+pub fn get_system_info(
+    backend: ContainerBackend,
+    docker_version: Option<String>,
+    compose: Option<ComposeInvoker>,
+) -> Result<SystemInfo, String> {
     let mut system_info = SystemInfo {
         os_type: std::env::consts::OS.to_string(),
         os_version: "Unknown".to_string(),
         hostname: "Unknown".to_string(),
         cpu_info: "Unknown".to_string(),
         memory_total: 0,
-        docker_version: None,
+        docker_version,
         docker_compose_version: None,
+        compose_provider: None,
+        container_backend: backend,
     };
 
     // Récupérer le nom d'hôte
@@ -487,23 +900,141 @@ pub fn get_system_info() -> Result<SystemInfo, String> {
         }
     }
 
-    // Version de Docker
-    if let Ok(output) = Command::new("docker").args(["--version"]).output() {
-        if output.status.success() {
-            if let Ok(docker_ver) = String::from_utf8(output.stdout) {
-                system_info.docker_version = Some(docker_ver.trim().to_string());
-            }
-        }
-    }
 
-    // Version de Docker Compose
-    if let Ok(output) = Command::new("docker-compose").args(["--version"]).output() {
-        if output.status.success() {
-            if let Ok(compose_ver) = String::from_utf8(output.stdout) {
-                system_info.docker_compose_version = Some(compose_ver.trim().to_string());
-            }
-        }
+    // Outil compose retenu et sa version
+    if let Some(compose) = compose {
+        system_info.compose_provider = Some(compose.provider);
+        system_info.docker_compose_version = Some(compose.version);
     }
 
     Ok(system_info)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_check_matches_names_not_substrings() {
+        let hosts = "127.0.0.1\tlocalhost shop.local.test  # dev\n\
+                     ::1 api.client.test\n\
+                     192.168.1.10 nas.local.test\n\
+                     127.0.0.1 blog.local.test.example\n\
+                     # 127.0.0.1 old.local.test\n";
+
+        assert!(hosts_file_resolves(hosts, "shop.local.test"));
+        assert!(hosts_file_resolves(hosts, "api.client.test"));
+        assert!(!hosts_file_resolves(hosts, "nas.local.test"));
+        assert!(!hosts_file_resolves(hosts, "blog.local.test"));
+        assert!(!hosts_file_resolves(hosts, "old.local.test"));
+    }
+
+    #[test]
+    fn detects_dns_forwarding_configs() {
+        let resolved =
+            vec!["[Resolve]\nDNS=127.0.0.1:5300\nDomains=~local.test ~corp\n".to_string()];
+        assert!(resolved_forwards(&resolved, "local.test", "127.0.0.1:5300"));
+        assert!(!resolved_forwards(&resolved, "dev.test", "127.0.0.1:5300"));
+        assert!(!resolved_forwards(&resolved, "local.test", "127.0.0.1:53"));
+
+        let dnsmasq = vec!["# dev-env\nserver=/local.test/127.0.0.1#5300\n".to_string()];
+        assert!(dnsmasq_forwards(&dnsmasq, "local.test", "127.0.0.1:5300"));
+        assert!(!dnsmasq_forwards(&dnsmasq, "local.test", "127.0.0.1:5353"));
+    }
+
+    #[test]
+    fn hosts_block_is_replaced_in_place() {
+        let hosts = "127.0.0.1 localhost\n# BEGIN dev-env\n127.0.0.1 old.local.test\n# END dev-env\n::1 localhost\n";
+        let entries = vec![
+            "127.0.0.1 local.test".to_string(),
+            "127.0.0.1 shop.local.test".to_string(),
+        ];
+
+        let updated = replace_hosts_block(hosts, &entries).unwrap();
+        assert_eq!(
+            updated,
+            "127.0.0.1 localhost\n# BEGIN dev-env\n127.0.0.1 local.test\n127.0.0.1 shop.local.test\n# END dev-env\n::1 localhost\n"
+        );
+        assert_eq!(
+            line_diff(hosts, &updated),
+            vec![
+                "-127.0.0.1 old.local.test",
+                "+127.0.0.1 local.test",
+                "+127.0.0.1 shop.local.test",
+            ]
+        );
+
+        assert_eq!(
+            replace_hosts_block("127.0.0.1 localhost", &entries[..1]).unwrap(),
+            "127.0.0.1 localhost\n# BEGIN dev-env\n127.0.0.1 local.test\n# END dev-env\n"
+        );
+        assert_eq!(
+            replace_hosts_block(&updated, &[]).unwrap(),
+            "127.0.0.1 localhost\n::1 localhost\n"
+        );
+    }
+
+    #[test]
+    fn unterminated_hosts_block_is_refused() {
+        let entries = vec!["127.0.0.1 shop.local.test".to_string()];
+        let unterminated =
+            "127.0.0.1 localhost\n# BEGIN dev-env\n127.0.0.1 old.local.test\n::1 localhost\n";
+        assert!(replace_hosts_block(unterminated, &entries).is_err());
+
+        let reversed = "# END dev-env\n127.0.0.1 localhost\n# BEGIN dev-env\n";
+        assert!(replace_hosts_block(reversed, &entries).is_err());
+    }
+
+    #[test]
+    fn hosts_line_endings_are_kept() {
+        let entries = vec!["127.0.0.1 shop.local.test".to_string()];
+        let crlf = "127.0.0.1 localhost\r\n# BEGIN dev-env\r\n127.0.0.1 shop.local.test\r\n# END dev-env\r\n::1 localhost";
+        assert_eq!(replace_hosts_block(crlf, &entries).unwrap(), crlf);
+
+        let entries = vec!["127.0.0.1 blog.local.test".to_string()];
+        assert_eq!(
+            replace_hosts_block(crlf, &entries).unwrap(),
+            "127.0.0.1 localhost\r\n# BEGIN dev-env\r\n127.0.0.1 blog.local.test\r\n# END dev-env\r\n::1 localhost"
+        );
+        assert_eq!(
+            replace_hosts_block("127.0.0.1 localhost\r\n::1 localhost", &entries).unwrap(),
+            "127.0.0.1 localhost\r\n::1 localhost\r\n# BEGIN dev-env\r\n127.0.0.1 blog.local.test\r\n# END dev-env\r\n"
+        );
+        assert_eq!(
+            replace_hosts_block("127.0.0.1 localhost\n\n", &[]).unwrap(),
+            "127.0.0.1 localhost\n\n"
+        );
+    }
+
+    #[test]
+    fn proc_net_keeps_listening_sockets() {
+        let tcp = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0050 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1234 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:0CEA 0100007F:D2F0 01 00000000:00000000 00:00000000 00000000  1000        0 5678 1 0000000000000000 20 4 30 10 -1
+";
+        assert_eq!(parse_proc_net(tcp, true), vec![(80, 1234)]);
+        assert_eq!(parse_proc_net(tcp, false), vec![(80, 1234), (3306, 5678)]);
+    }
+
+    #[test]
+    fn ports_held_by_the_environment_are_not_conflicts() {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let compose = ComposeFile::from_yaml(&format!(
+            "services:\n  web:\n    image: nginx\n    ports: [\"{}:80\"]\n",
+            port
+        ))
+        .unwrap();
+
+        let conflicts = check_port_conflicts(&compose, &[]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].port, port);
+        assert_eq!(conflicts[0].service, "web");
+
+        assert!(check_port_conflicts(&compose, &[(port, "tcp".to_string())]).is_empty());
+        assert_eq!(
+            check_port_conflicts(&compose, &[(port, "udp".to_string())]).len(),
+            1
+        );
+    }
+}
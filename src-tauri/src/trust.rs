@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::certs;
+use crate::system;
+
+// Installation de la CA locale dans les magasins de confiance Linux :
+// le magasin système (selon la distribution) et les bases NSS de Firefox et Chromium
+
+const ANCHOR_FILE: &str = "dev-env-local-ca.crt";
+
+// Magasin système de la distribution, lu depuis /etc/os-release
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum SystemTrustStore {
+    // Dossier d'ancres puis commande de régénération du bundle
+    Anchors { dir: String, update: Vec<String> },
+    // Distribution inconnue : p11-kit gère lui-même ses ancres
+    P11Kit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NssDatabaseStatus {
+    pub path: String,
+    pub installed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrustStatus {
+    pub ca_exists: bool,
+    pub system_store: Option<SystemTrustStore>,
+    pub system_installed: bool,
+    pub certutil_available: bool,
+    pub nss_databases: Vec<NssDatabaseStatus>,
+}
+
+fn anchors(dir: &str, update: &[&str]) -> SystemTrustStore {
+    SystemTrustStore::Anchors {
+        dir: dir.to_string(),
+        update: update.iter().map(|arg| arg.to_string()).collect(),
+    }
+}
+
+// ID et ID_LIKE de os-release, la distribution elle-même en premier
+fn os_release_ids(content: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    for key in ["ID", "ID_LIKE"] {
+        let prefix = format!("{}=", key);
+        if let Some(value) = content.lines().find_map(|line| line.strip_prefix(&prefix)) {
+            ids.extend(
+                value
+                    .trim_matches('"')
+                    .split_whitespace()
+                    .map(|id| id.to_lowercase()),
+            );
+        }
+    }
+    ids
+}
+
+pub fn detect_system_store(os_release: &str) -> Option<SystemTrustStore> {
+    for id in os_release_ids(os_release) {
+        let store = match id.as_str() {
+            "debian" | "ubuntu" => anchors(
+                "/usr/local/share/ca-certificates",
+                &["update-ca-certificates"],
+            ),
+            "fedora" | "rhel" | "centos" => anchors(
+                "/etc/pki/ca-trust/source/anchors",
+                &["update-ca-trust", "extract"],
+            ),
+            "arch" => anchors(
+                "/etc/ca-certificates/trust-source/anchors",
+                &["update-ca-trust"],
+            ),
+            "suse" | "opensuse" => anchors("/etc/pki/trust/anchors", &["update-ca-certificates"]),
+            _ => continue,
+        };
+        return Some(store);
+    }
+    if system::command_exists("trust") {
+        Some(SystemTrustStore::P11Kit)
+    } else {
+        None
+    }
+}
+
+fn current_system_store() -> Option<SystemTrustStore> {
+    let os_release = fs::read_to_string("/etc/os-release").unwrap_or_default();
+    detect_system_store(&os_release)
+}
+
+// Empreinte SHA-256 de la CA actuelle : une ancienne racine du même nom ne compte pas
+fn ca_fingerprint(ca_pem: &str) -> Option<String> {
+    certs::pem_fingerprints(ca_pem.as_bytes())
+        .into_iter()
+        .next()
+}
+
+// URI pkcs11 des ancres p11-kit portant ce libellé, dans la sortie de `trust list`
+fn p11kit_anchor_uris(list: &str, label: &str) -> Vec<String> {
+    let label_line = format!("label: {}", label);
+    let mut uris = Vec::new();
+    let mut current = None;
+    for line in list.lines() {
+        if line.starts_with("pkcs11:") {
+            current = Some(line.trim());
+        } else if line.trim() == label_line {
+            uris.extend(current.map(str::to_string));
+        }
+    }
+    uris
+}
+
+fn remove_p11kit_anchors() -> Result<(), String> {
+    let output = Command::new("trust")
+        .args(["list", "--filter=ca-anchors"])
+        .output()
+        .map_err(|e| format!("Failed to run trust: {}", e))?;
+    let list = String::from_utf8_lossy(&output.stdout);
+    for uri in p11kit_anchor_uris(&list, certs::CA_COMMON_NAME) {
+        system::run_privileged("trust", &["anchor", "--remove", &uri])?;
+    }
+    Ok(())
+}
+
+fn system_store_installed(store: &SystemTrustStore, ca_pem: &str) -> bool {
+    match store {
+        SystemTrustStore::Anchors { dir, .. } => {
+            fs::read_to_string(Path::new(dir).join(ANCHOR_FILE))
+                .map(|installed| installed == ca_pem)
+                .unwrap_or(false)
+        }
+        // `trust dump` donne les ancres au format PEM
+        SystemTrustStore::P11Kit => match ca_fingerprint(ca_pem) {
+            Some(fingerprint) => Command::new("trust")
+                .args(["dump", "--filter=ca-anchors"])
+                .output()
+                .map(|output| certs::pem_fingerprints(&output.stdout).contains(&fingerprint))
+                .unwrap_or(false),
+            None => false,
+        },
+    }
+}
+
+fn install_system(store: &SystemTrustStore, ca_path: &Path) -> Result<(), String> {
+    let ca_path = ca_path.to_string_lossy();
+    match store {
+        SystemTrustStore::Anchors { dir, update } => {
+            let target = Path::new(dir).join(ANCHOR_FILE);
+            system::run_privileged(
+                "install",
+                &["-D", "-m", "644", &ca_path, &target.to_string_lossy()],
+            )?;
+            let args: Vec<&str> = update[1..].iter().map(String::as_str).collect();
+            system::run_privileged(&update[0], &args)
+        }
+        // Une ancienne racine du même nom est retirée avant d'ajouter l'actuelle
+        SystemTrustStore::P11Kit => {
+            remove_p11kit_anchors()?;
+            system::run_privileged("trust", &["anchor", "--store", &ca_path])
+        }
+    }
+}
+
+fn uninstall_system(store: &SystemTrustStore) -> Result<(), String> {
+    match store {
+        SystemTrustStore::Anchors { dir, update } => {
+            let target = Path::new(dir).join(ANCHOR_FILE);
+            if !target.exists() {
+                return Ok(());
+            }
+            system::run_privileged("rm", &["-f", &target.to_string_lossy()])?;
+            let args: Vec<&str> = update[1..].iter().map(String::as_str).collect();
+            system::run_privileged(&update[0], &args)
+        }
+        SystemTrustStore::P11Kit => remove_p11kit_anchors(),
+    }
+}
+
+// Bases NSS de l'utilisateur : Chromium partage ~/.pki/nssdb, Firefox en a une
+// par profil (installation classique, snap ou flatpak)
+fn nss_databases(home: &Path) -> Vec<PathBuf> {
+    let mut databases = Vec::new();
+    let chromium = home.join(".pki").join("nssdb");
+    if chromium.join("cert9.db").exists() {
+        databases.push(chromium);
+    }
+
+    let firefox_dirs = [
+        home.join(".mozilla/firefox"),
+        home.join("snap/firefox/common/.mozilla/firefox"),
+        home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"),
+    ];
+    for dir in firefox_dirs {
+        let mut profiles: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|profile| profile.join("cert9.db").exists())
+                    .collect()
+            })
+            .unwrap_or_default();
+        profiles.sort();
+        databases.extend(profiles);
+    }
+    databases
+}
+
+// Empreintes des certificats enregistrés sous le nom de la CA dans une base NSS
+fn nss_fingerprints(database: &Path) -> Vec<String> {
+    Command::new("certutil")
+        .args(["-L", "-n", certs::CA_COMMON_NAME, "-a", "-d"])
+        .arg(format!("sql:{}", database.display()))
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| certs::pem_fingerprints(&output.stdout))
+        .unwrap_or_default()
+}
+
+fn certutil(database: &Path, args: &[&str]) -> Result<(), String> {
+    let output = Command::new("certutil")
+        .args(args)
+        .arg("-d")
+        .arg(format!("sql:{}", database.display()))
+        .output()
+        .map_err(|e| format!("Failed to run certutil: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "certutil failed on '{}': {}",
+            database.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn home_dir() -> Result<PathBuf, String> {
+    dirs::home_dir().ok_or_else(|| "Could not determine home directory".to_string())
+}
+
+fn ensure_linux() -> Result<(), String> {
+    if cfg!(target_os = "linux") {
+        Ok(())
+    } else {
+        Err("Installing the local CA is only supported on Linux".to_string())
+    }
+}
+
+pub fn status() -> Result<TrustStatus, String> {
+    let ca_path = certs::ca_cert_path()?;
+    let ca_pem = fs::read_to_string(&ca_path).ok();
+    let system_store = current_system_store();
+    let certutil_available = system::command_exists("certutil");
+
+    let system_installed = match (&system_store, &ca_pem) {
+        (Some(store), Some(pem)) => system_store_installed(store, pem),
+        _ => false,
+    };
+    let fingerprint = ca_pem.as_deref().and_then(ca_fingerprint);
+    let nss_databases = nss_databases(&home_dir()?)
+        .into_iter()
+        .map(|database| NssDatabaseStatus {
+            installed: certutil_available
+                && fingerprint
+                    .as_ref()
+                    .is_some_and(|fingerprint| nss_fingerprints(&database).contains(fingerprint)),
+            path: database.display().to_string(),
+        })
+        .collect();
+
+    Ok(TrustStatus {
+        ca_exists: ca_pem.is_some(),
+        system_store,
+        system_installed,
+        certutil_available,
+        nss_databases,
+    })
+}
+
+pub fn install() -> Result<TrustStatus, String> {
+    ensure_linux()?;
+    let ca_path = certs::ca_cert_path()?;
+    let ca_pem = fs::read_to_string(&ca_path)
+        .map_err(|_| "Local CA not found. Generate configuration first.".to_string())?;
+    let fingerprint =
+        ca_fingerprint(&ca_pem).ok_or_else(|| "Failed to parse the local CA".to_string())?;
+    let store = current_system_store()
+        .ok_or_else(|| "No supported system trust store was found".to_string())?;
+    install_system(&store, &ca_path)?;
+
+    // Sans certutil, seul le magasin système est mis à jour ; le statut le signale
+    if system::command_exists("certutil") {
+        let ca = ca_path.to_string_lossy();
+        for database in nss_databases(&home_dir()?) {
+            let installed = nss_fingerprints(&database);
+            if installed.contains(&fingerprint) {
+                continue;
+            }
+            // Une ancienne racine du même nom est remplacée
+            for _ in &installed {
+                certutil(&database, &["-D", "-n", certs::CA_COMMON_NAME])?;
+            }
+            certutil(
+                &database,
+                &["-A", "-t", "C,,", "-n", certs::CA_COMMON_NAME, "-i", &ca],
+            )?;
+        }
+    }
+
+    status()
+}
+
+pub fn uninstall() -> Result<TrustStatus, String> {
+    ensure_linux()?;
+    if let Some(store) = current_system_store() {
+        uninstall_system(&store)?;
+    }
+
+    if system::command_exists("certutil") {
+        for database in nss_databases(&home_dir()?) {
+            for _ in nss_fingerprints(&database) {
+                certutil(&database, &["-D", "-n", certs::CA_COMMON_NAME])?;
+            }
+        }
+    }
+
+    status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_store_follows_os_release() {
+        let ubuntu = "NAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE=debian\n";
+        assert_eq!(
+            detect_system_store(ubuntu),
+            Some(anchors(
+                "/usr/local/share/ca-certificates",
+                &["update-ca-certificates"]
+            ))
+        );
+
+        let rocky = "ID=\"rocky\"\nID_LIKE=\"rhel centos fedora\"\n";
+        assert_eq!(
+            detect_system_store(rocky),
+            Some(anchors(
+                "/etc/pki/ca-trust/source/anchors",
+                &["update-ca-trust", "extract"]
+            ))
+        );
+
+        let manjaro = "ID=manjaro\nID_LIKE=arch\n";
+        assert_eq!(
+            detect_system_store(manjaro),
+            Some(anchors(
+                "/etc/ca-certificates/trust-source/anchors",
+                &["update-ca-trust"]
+            ))
+        );
+    }
+
+    #[test]
+    fn p11kit_anchors_are_found_by_label() {
+        let list = "pkcs11:id=%01%02;type=cert\n    type: certificate\n    label: ISRG Root X1\n    trust: anchor\n    category: authority\n\npkcs11:id=%AA%BB;type=cert\n    type: certificate\n    label: dev-env local CA\n    trust: anchor\n    category: authority\n";
+        assert_eq!(
+            p11kit_anchor_uris(list, certs::CA_COMMON_NAME),
+            vec!["pkcs11:id=%AA%BB;type=cert".to_string()]
+        );
+    }
+}